@@ -0,0 +1,77 @@
+use crate::game::*;
+
+/// The player inputs which drive a game. Scripts, replays and the terminal front end all feed the
+/// game through these.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    RotateLeft,
+    RotateRight,
+    Rotate180,
+    SoftDropStart,
+    SoftDropStop,
+    HardDrop,
+    Hold,
+    ShiftLeftToWall,
+    ShiftRightToWall,
+}
+
+impl Action {
+    /// Every action in a fixed order. An action's index in this list is its id in replay files so
+    /// new actions must only ever be added to the end.
+    pub const ALL: [Action; 11] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::RotateLeft,
+        Action::RotateRight,
+        Action::SoftDropStart,
+        Action::SoftDropStop,
+        Action::HardDrop,
+        Action::Hold,
+        Action::ShiftLeftToWall,
+        Action::ShiftRightToWall,
+        Action::Rotate180,
+    ];
+
+    /// Looks up an action by the name used in input scripts
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "left",
+            Action::MoveRight => "right",
+            Action::RotateLeft => "rotate_left",
+            Action::RotateRight => "rotate_right",
+            Action::Rotate180 => "rotate_180",
+            Action::SoftDropStart => "soft_drop_start",
+            Action::SoftDropStop => "soft_drop_stop",
+            Action::HardDrop => "hard_drop",
+            Action::Hold => "hold",
+            Action::ShiftLeftToWall => "left_to_wall",
+            Action::ShiftRightToWall => "right_to_wall",
+        }
+    }
+
+    pub fn apply(&self, game_state: &mut GameState) {
+        match self {
+            Action::MoveLeft => game_state.move_active_block_horizontal(-1),
+            Action::MoveRight => game_state.move_active_block_horizontal(1),
+            // NOTE: Unwrap is safe because quarter and half turns are always valid rotations
+            Action::RotateLeft => game_state.rotate_block(-1).unwrap(),
+            Action::RotateRight => game_state.rotate_block(1).unwrap(),
+            Action::Rotate180 => game_state.rotate_block(2).unwrap(),
+            Action::SoftDropStart => game_state.set_soft_drop(true),
+            Action::SoftDropStop => game_state.set_soft_drop(false),
+            Action::HardDrop => game_state.hard_drop(),
+            Action::Hold => game_state.hold(),
+            Action::ShiftLeftToWall => game_state.shift_active_block_to_wall(-1),
+            Action::ShiftRightToWall => game_state.shift_active_block_to_wall(1),
+        }
+    }
+}
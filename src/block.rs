@@ -1,7 +1,9 @@
+use crate::rotation::*;
 use crate::util::*;
-use snm_rand_utils::range_rng::*;
 
-#[derive(Clone, Copy, Debug)]
+/// The four orientations a block can be in. Every step after `Rot0` is a quarter turn clockwise.
+/// Which one a block spawns in is up to its [`RotationSystem`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Rotation {
     Rot0,
     Rot1,
@@ -9,6 +11,7 @@ pub enum Rotation {
     Rot3,
 }
 
+/// The seven tetrominoes
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BlockType {
     I = 1, // NOTE (scottnm): if our enum starts at 0, init_pair doesn't seem to function. Needs investigation
@@ -20,22 +23,16 @@ pub enum BlockType {
     L,
 }
 
+/// A tetromino in a given orientation. Its cells are relative to the top-left of its bounding box
+/// and laid out by its rotation system.
 #[derive(Clone, Copy, Debug)]
 pub struct Block {
     pub rot: Rotation,
     pub block_type: BlockType,
+    pub rotation_system: &'static dyn RotationSystem,
 }
 
-macro_rules! cell_array {
-    ( $(($x:expr,$y:expr)),* $(,)?) => {
-        [
-            $(
-                Vec2{x: $x, y: $y},
-            )*
-        ]
-    };
-}
-
+/// Every block type in order
 pub static BLOCKTYPES: [BlockType; 7] = [
     BlockType::I,
     BlockType::O,
@@ -47,99 +44,30 @@ pub static BLOCKTYPES: [BlockType; 7] = [
 ];
 
 impl Rotation {
-    fn rotate(&self, relative_rotation: i32) -> Self {
-        enum RotationDirection {
-            None,
-            Left,
-            Right,
+    /// The orientation after turning left (-1), right (1) or halfway round (2 or -2). Any other
+    /// number of quarter turns is an error.
+    pub fn rotate(&self, relative_rotation: i32) -> Result<Self, String> {
+        const ROTATIONS: [Rotation; 4] = [
+            Rotation::Rot0,
+            Rotation::Rot1,
+            Rotation::Rot2,
+            Rotation::Rot3,
+        ];
+
+        if !(-2..=2).contains(&relative_rotation) {
+            return Err(format!(
+                "can't turn a block {} quarter turns, only -2 to 2",
+                relative_rotation
+            ));
         }
 
-        let rotation_direction = match relative_rotation {
-            0 => RotationDirection::None,
-            -1 => RotationDirection::Left,
-            1 => RotationDirection::Right,
-            _ => panic!("Invalid relative rotation"),
-        };
-
-        match rotation_direction {
-            RotationDirection::None => *self,
-            RotationDirection::Left => match self {
-                Rotation::Rot0 => Rotation::Rot3,
-                Rotation::Rot1 => Rotation::Rot0,
-                Rotation::Rot2 => Rotation::Rot1,
-                Rotation::Rot3 => Rotation::Rot2,
-            },
-            RotationDirection::Right => match self {
-                Rotation::Rot0 => Rotation::Rot1,
-                Rotation::Rot1 => Rotation::Rot2,
-                Rotation::Rot2 => Rotation::Rot3,
-                Rotation::Rot3 => Rotation::Rot0,
-            },
-        }
-    }
-
-    pub fn get_kick_attempts(&self, block: BlockType, dest_rot: Rotation) -> [Vec2; 5] {
-        match block {
-            BlockType::O => panic!("O blocks do not need to be kicked"),
-            BlockType::I => match (*self, dest_rot) {
-                (Rotation::Rot0, Rotation::Rot1) => {
-                    cell_array![(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)]
-                }
-                (Rotation::Rot1, Rotation::Rot0) => {
-                    cell_array![(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)]
-                }
-                (Rotation::Rot1, Rotation::Rot2) => {
-                    cell_array![(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)]
-                }
-                (Rotation::Rot2, Rotation::Rot1) => {
-                    cell_array![(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)]
-                }
-                (Rotation::Rot2, Rotation::Rot3) => {
-                    cell_array![(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)]
-                }
-                (Rotation::Rot3, Rotation::Rot2) => {
-                    cell_array![(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)]
-                }
-                (Rotation::Rot3, Rotation::Rot0) => {
-                    cell_array![(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)]
-                }
-                (Rotation::Rot0, Rotation::Rot3) => {
-                    cell_array![(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)]
-                }
-                (r1, r2) => panic!("{:?} >> {:?} is an invalid rotation", r1, r2),
-            },
-            _ => match (*self, dest_rot) {
-                (Rotation::Rot0, Rotation::Rot1) => {
-                    cell_array![(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]
-                }
-                (Rotation::Rot1, Rotation::Rot0) => {
-                    cell_array![(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)]
-                }
-                (Rotation::Rot1, Rotation::Rot2) => {
-                    cell_array![(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)]
-                }
-                (Rotation::Rot2, Rotation::Rot1) => {
-                    cell_array![(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]
-                }
-                (Rotation::Rot2, Rotation::Rot3) => {
-                    cell_array![(0, 0), (1, 0), (1, 1), (0, -2), (-1, -2)]
-                }
-                (Rotation::Rot3, Rotation::Rot2) => {
-                    cell_array![(0, 0), (-1, 0), (-1, -1), (0, 2), (1, -2)]
-                }
-                (Rotation::Rot3, Rotation::Rot0) => {
-                    cell_array![(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]
-                }
-                (Rotation::Rot0, Rotation::Rot3) => {
-                    cell_array![(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)]
-                }
-                (r1, r2) => panic!("{:?} >> {:?} is an invalid rotation", r1, r2),
-            },
-        }
+        let index = ROTATIONS.iter().position(|rot| rot == self).unwrap() as i32;
+        Ok(ROTATIONS[(index + relative_rotation).rem_euclid(4) as usize])
     }
 }
 
 impl BlockType {
+    /// The character used to draw this block type
     pub fn sprite_char(&self) -> char {
         match *self {
             BlockType::I => 'O',
@@ -153,18 +81,24 @@ impl BlockType {
     }
 }
 
+impl Default for Block {
+    fn default() -> Self {
+        Block::new(BlockType::I)
+    }
+}
+
 impl Block {
-    pub fn default() -> Self {
-        Block {
-            rot: Rotation::Rot0,
-            block_type: BlockType::I,
-        }
+    /// A block in its spawn orientation under the guideline rotation system
+    pub fn new(block_type: BlockType) -> Self {
+        Self::spawn(block_type, &SRS)
     }
 
-    pub fn random(rng: &mut dyn RangeRng<usize>) -> Self {
+    /// A block in its spawn orientation under the given rotation system
+    pub fn spawn(block_type: BlockType, rotation_system: &'static dyn RotationSystem) -> Self {
         Block {
-            rot: Rotation::Rot0,
-            block_type: BLOCKTYPES[rng.gen_range(1, BLOCKTYPES.len() + 1) - 1],
+            rot: rotation_system.spawn_rotation(block_type),
+            block_type,
+            rotation_system,
         }
     }
 
@@ -172,76 +106,9 @@ impl Block {
         self.block_type.sprite_char()
     }
 
+    /// The positions of the block's 4 cells relative to the block
     pub fn cells(&self) -> [Vec2; 4] {
-        let rot = self.rot;
-        match self.block_type {
-            // - - - -    - - 0 -    - - - -    - 0 - -
-            // 0 1 2 3 => - - 1 - => - - - - => - 1 - -
-            // - - - -    - - 2 -    0 1 2 3    - 2 - -
-            // - - - -    - - 3 -    - - - -    - 3 - -
-            BlockType::I => match rot {
-                Rotation::Rot0 => cell_array![(0, 1), (1, 1), (2, 1), (3, 1),],
-                Rotation::Rot1 => cell_array![(2, 0), (2, 1), (2, 2), (2, 3),],
-                Rotation::Rot2 => cell_array![(0, 2), (1, 2), (2, 2), (3, 2),],
-                Rotation::Rot3 => cell_array![(1, 0), (1, 1), (1, 2), (1, 3),],
-            },
-
-            // - 0 1 -    - 0 1 -    - 0 1 -    - 0 1 -
-            // - 2 3 - => - 2 3 - => - 2 3 - => - 2 3 -
-            // - - - -    - - - -    - - - -    - - - -
-            // - - - -    - - - -    - - - -    - - - -
-            BlockType::O => cell_array![(1, 0), (2, 0), (1, 1), (2, 1),],
-
-            // - 0 -    - 0 -    - - -    - 0 -
-            // 1 2 3 => - 1 2 => 0 1 2 => 1 2 -
-            // - - -    - 3 -    - 3 -    - 3 -
-            BlockType::T => match rot {
-                Rotation::Rot0 => cell_array![(1, 0), (0, 1), (1, 1), (2, 1),],
-                Rotation::Rot1 => cell_array![(1, 0), (1, 1), (2, 1), (1, 2),],
-                Rotation::Rot2 => cell_array![(0, 1), (1, 1), (2, 1), (1, 2),],
-                Rotation::Rot3 => cell_array![(1, 0), (0, 1), (1, 1), (1, 2),],
-            },
-
-            // - 0 1    - 0 -    - - -    0 - -
-            // 2 3 - => - 1 2 => - 0 1 => 1 2 -
-            // - - -    - - 3    2 3 -    - 3 -
-            BlockType::S => match rot {
-                Rotation::Rot0 => cell_array![(1, 0), (2, 0), (0, 1), (1, 1),],
-                Rotation::Rot1 => cell_array![(1, 0), (1, 1), (2, 1), (2, 2),],
-                Rotation::Rot2 => cell_array![(1, 1), (2, 1), (0, 2), (1, 2),],
-                Rotation::Rot3 => cell_array![(0, 0), (0, 1), (1, 1), (1, 2),],
-            },
-
-            // 0 1 -    - - 0    - - -    - 0 -
-            // - 2 3 => - 1 2 => 0 1 - => 1 2 -
-            // - - -    - 3 -    - 2 3    3 - -
-            BlockType::Z => match rot {
-                Rotation::Rot0 => cell_array![(0, 0), (1, 0), (1, 1), (2, 1),],
-                Rotation::Rot1 => cell_array![(2, 0), (1, 1), (2, 1), (1, 2),],
-                Rotation::Rot2 => cell_array![(0, 1), (1, 1), (1, 2), (2, 2),],
-                Rotation::Rot3 => cell_array![(1, 0), (0, 1), (1, 1), (0, 2),],
-            },
-
-            // 0 - -    - 0 1    - - -    - 0 -
-            // 1 2 3 => - 2 - => 0 1 2 => - 1 -
-            // - - -    - 3 -    - - 3    2 3 -
-            BlockType::J => match rot {
-                Rotation::Rot0 => cell_array![(0, 0), (0, 1), (1, 1), (2, 1),],
-                Rotation::Rot1 => cell_array![(1, 0), (2, 0), (1, 1), (1, 2),],
-                Rotation::Rot2 => cell_array![(0, 1), (1, 1), (2, 1), (2, 2),],
-                Rotation::Rot3 => cell_array![(1, 0), (1, 1), (0, 2), (1, 2),],
-            },
-
-            // - - 0    - 0 -    - - -    0 1 -
-            // 1 2 3 => - 1 - => 0 1 2 => - 2 -
-            // - - -    - 2 3    3 - -    - 3 -
-            BlockType::L => match rot {
-                Rotation::Rot0 => cell_array![(2, 0), (0, 1), (1, 1), (2, 1),],
-                Rotation::Rot1 => cell_array![(1, 0), (1, 1), (1, 2), (2, 2),],
-                Rotation::Rot2 => cell_array![(0, 1), (1, 1), (2, 1), (0, 2),],
-                Rotation::Rot3 => cell_array![(0, 0), (1, 0), (1, 1), (1, 2),],
-            },
-        }
+        self.rotation_system.cells(self.block_type, self.rot)
     }
 
     pub fn top(&self) -> i32 {
@@ -268,10 +135,11 @@ impl Block {
         bottom_block - top_block + 1
     }
 
-    pub fn rotate(&self, relative_rotation: i32) -> Self {
-        Self {
-            rot: self.rot.rotate(relative_rotation),
-            block_type: self.block_type,
-        }
+    /// The same block turned left (-1), right (1) or halfway round (2 or -2)
+    pub fn rotate(&self, relative_rotation: i32) -> Result<Self, String> {
+        Ok(Self {
+            rot: self.rot.rotate(relative_rotation)?,
+            ..*self
+        })
     }
 }
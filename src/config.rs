@@ -0,0 +1,110 @@
+use crate::game::*;
+use crate::handling::*;
+use crate::keybindings::*;
+use crate::pacing::*;
+
+/// The player's settings. Config files are a small subset of TOML with a `[keys]` table for the
+/// key bindings (see [`KeyBindings`]), a `[handling]` table for how held keys repeat (see
+/// [`Handling`]), a `[display]` table for the frame rate (see [`Display`]) and a `[rules]` table
+/// for the rotation system (see [`Rules`]). Anything left out keeps its default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    pub key_bindings: KeyBindings,
+    pub handling: Handling,
+    pub display: Display,
+    pub rules: Rules,
+}
+
+impl Config {
+    pub fn parse<S: AsRef<str>>(config: S) -> Result<Self, String> {
+        Ok(Config {
+            key_bindings: KeyBindings::parse(config.as_ref())?,
+            handling: Handling::parse(config.as_ref())?,
+            display: Display::parse(config.as_ref())?,
+            rules: Rules::parse(config.as_ref())?,
+        })
+    }
+
+    /// Reads a config file, falling back to the defaults if there isn't one
+    pub fn load<S: AsRef<str>>(file_name: S) -> Result<Self, String> {
+        match std::fs::read_to_string(file_name.as_ref()) {
+            Ok(config) => Self::parse(config).map_err(|e| format!("{}: {}", file_name.as_ref(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("failed to read {}: {}", file_name.as_ref(), e)),
+        }
+    }
+
+    /// Writes every setting to a config file, creating its directory if needed
+    pub fn save<S: AsRef<str>>(&self, file_name: S) -> Result<(), String> {
+        let path = std::path::Path::new(file_name.as_ref());
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+        }
+
+        std::fs::write(path, self.to_config_string())
+            .map_err(|e| format!("failed to write {}: {}", file_name.as_ref(), e))
+    }
+
+    pub fn to_config_string(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n{}",
+            self.key_bindings.to_config_string(),
+            self.handling.to_config_string(),
+            self.display.to_config_string(),
+            self.rules.to_config_string()
+        )
+    }
+}
+
+// Calls `parse_entry` with the name and value of every `name = value` line in one table of a
+// config file. Other tables are skipped so each setting only has to know about its own table.
+pub(crate) fn parse_table<F>(config: &str, table: &str, mut parse_entry: F) -> Result<(), String>
+where
+    F: FnMut(&str, &str) -> Result<(), String>,
+{
+    let table_header = format!("[{}]", table);
+    let mut in_table = false;
+
+    for (line_index, line) in config.lines().enumerate() {
+        let line_number = line_index + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            in_table = line == table_header;
+            continue;
+        }
+
+        if !in_table {
+            continue;
+        }
+
+        let (name, value) = match line.find('=') {
+            Some(split) => (line[..split].trim(), line[split + 1..].trim()),
+            None => return Err(format!("line {}: expected '<name> = <value>'", line_number)),
+        };
+
+        parse_entry(name, value).map_err(|e| format!("line {}: {}", line_number, e))?;
+    }
+
+    Ok(())
+}
+
+// Drops a trailing '#' comment, leaving any '#' inside quotes alone
+fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, ch) in line.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '#' if !in_quotes => return &line[..i],
+            _ => (),
+        }
+    }
+    line
+}
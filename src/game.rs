@@ -1,181 +1,619 @@
 use crate::block::*;
+use crate::config::*;
+use crate::piece_generator::*;
+use crate::rotation::*;
+use crate::seeded_rng::*;
 use crate::util::*;
 use snm_rand_utils::range_rng::*;
+use std::collections::VecDeque;
+use std::time::Duration;
 
 #[derive(PartialEq, Eq)]
 enum GamePhase {
-    StartNextBlock,
+    // waiting out the entry delay (ARE) before the next block spawns
+    Spawning,
     MoveBlock,
-    GameOver,
+    // cleared rows stay on the board until the line clear delay is over
+    LineClear,
+    GameOver(GameOverReason),
 }
 
-#[derive(Clone, Copy)]
-enum Bound {
-    Floor(i32),
-    LeftWall(i32),
-    RightWall(i32),
+/// Why a game ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameOverReason {
+    /// a block locked above the top of the board
+    ToppedOut,
+    /// the mode's line goal was cleared
+    GoalComplete,
+    /// the mode's time limit ran out
+    TimeUp,
 }
 
+/// How long a block may rest on the stack before it locks in place. Moving or rotating a resting
+/// block restarts the delay, but only up to `max_resets` times before the block locks as soon as it
+/// touches down. Falling to a new lowest row earns back all of the resets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LockDelay {
+    pub duration: Duration,
+    /// None allows a block to be reset forever ("infinite lock")
+    pub max_resets: Option<usize>,
+}
+
+impl LockDelay {
+    /// Locks after 500ms with up to 15 resets
+    pub const fn guideline() -> Self {
+        LockDelay {
+            duration: Duration::from_millis(500),
+            max_resets: Some(15),
+        }
+    }
+
+    /// Locks after 500ms but can be reset forever
+    pub const fn infinite() -> Self {
+        LockDelay {
+            duration: Duration::from_millis(500),
+            max_resets: None,
+        }
+    }
+
+    /// Lock as soon as a resting block sees another update
+    pub const fn instant() -> Self {
+        LockDelay {
+            duration: Duration::from_millis(0),
+            max_resets: Some(0),
+        }
+    }
+}
+
+/// How long the game waits after a block locks. Cleared rows stay on the board for `line_clear`
+/// so they can be animated, and then the next block spawns once `entry` (ARE) has passed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpawnDelay {
+    pub entry: Duration,
+    pub line_clear: Duration,
+}
+
+impl SpawnDelay {
+    /// A 100ms entry delay and 300ms to show cleared rows
+    pub const fn standard() -> Self {
+        SpawnDelay {
+            entry: Duration::from_millis(100),
+            line_clear: Duration::from_millis(300),
+        }
+    }
+
+    /// Cleared rows disappear as soon as the block locks and the next block spawns on the next
+    /// update
+    pub const fn instant() -> Self {
+        SpawnDelay {
+            entry: Duration::from_millis(0),
+            line_clear: Duration::from_millis(0),
+        }
+    }
+}
+
+/// The rules games are played by, from the `[rules]` table of a config file:
+///
+/// ```text
+/// [rules]
+/// rotation_system = "ars"
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rules {
+    /// how blocks spawn, turn and kick: "srs", "ars" or "classic"
+    pub rotation_system: RotationSystemType,
+}
+
+impl Rules {
+    /// Reads the `[rules]` table of a config file, starting from the defaults
+    pub fn parse<S: AsRef<str>>(config: S) -> Result<Self, String> {
+        let mut rules = Rules::default();
+        parse_table(config.as_ref(), "rules", |name, value| {
+            match name {
+                "rotation_system" => {
+                    rules.rotation_system = value
+                        .strip_prefix('"')
+                        .and_then(|value| value.strip_suffix('"'))
+                        .and_then(RotationSystemType::from_name)
+                        .ok_or_else(|| {
+                            format!(
+                                "rotation_system must be srs, ars or classic but was {}",
+                                value
+                            )
+                        })?
+                }
+                _ => return Err(format!("unknown rules setting '{}'", name)),
+            }
+            Ok(())
+        })?;
+
+        Ok(rules)
+    }
+
+    pub fn to_config_string(&self) -> String {
+        format!(
+            "[rules]\nrotation_system = \"{}\"\n",
+            self.rotation_system.name()
+        )
+    }
+}
+
+/// What kind of T-spin a locked T block made
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TSpin {
+    None,
+    Mini,
+    Full,
+}
+
+/// The result of locking a block which cleared lines and/or was a T-spin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineClear {
+    pub lines: usize,
+    pub t_spin: TSpin,
+    /// whether this clear followed another difficult clear and earned the back-to-back bonus
+    pub back_to_back: bool,
+    /// how many blocks in a row (including this one) have cleared lines
+    pub combo: usize,
+    pub score: usize,
+}
+
+impl LineClear {
+    /// Tetrises and T-spins which clear lines are "difficult" and chain into back-to-backs
+    pub fn is_difficult(&self) -> bool {
+        self.lines == 4 || (self.lines > 0 && self.t_spin != TSpin::None)
+    }
+}
+
+/// Notable things which happened during a game update that the UI may want to react to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    LineClear(LineClear),
+    /// A line clear which left the board completely empty, along with the bonus it earned
+    PerfectClear {
+        lines: usize,
+        score: usize,
+    },
+}
+
+/// The rules which decide when a game ends. Every mode can also end by topping out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameMode {
+    /// play until topping out
+    Marathon,
+    /// race to clear a set number of lines
+    Sprint { line_goal: usize },
+    /// score as much as possible before the time runs out
+    Ultra { time_limit: Duration },
+}
+
+impl GameMode {
+    /// Lines to clear in a standard sprint
+    pub const DEFAULT_SPRINT_LINE_GOAL: usize = 40;
+    /// How many lines apart a sprint's splits are taken
+    pub const SPRINT_SPLIT_INTERVAL: usize = 10;
+    /// How long a standard ultra lasts
+    pub const DEFAULT_ULTRA_TIME_LIMIT: Duration = Duration::from_secs(2 * 60);
+
+    /// A sprint to the default 40 lines
+    pub fn sprint() -> Self {
+        GameMode::Sprint {
+            line_goal: Self::DEFAULT_SPRINT_LINE_GOAL,
+        }
+    }
+
+    /// An ultra with the default 2 minute time limit
+    pub fn ultra() -> Self {
+        GameMode::Ultra {
+            time_limit: Self::DEFAULT_ULTRA_TIME_LIMIT,
+        }
+    }
+}
+
+/// The full state of a game of tetris: the board, the falling block, the queue and the score. The
+/// game only moves forward when [`GameState::update`] is called with however much time has passed,
+/// so it can be driven by a real-time front end or stepped through by tools and tests.
 pub struct GameState {
     board_width: i32,
     board_height: i32,
-    block_type_rng: Box<dyn RangeRng<usize>>,
+    piece_generator: Box<dyn PieceGenerator>,
+    rotation_system: &'static dyn RotationSystem,
     settled_cells: Box<[Option<BlockType>]>,
-    next_block: Block,
+    next_blocks: VecDeque<Block>,
+    hold_block: Option<Block>,
+    can_hold: bool,
     active_block: Block,
     active_block_pos: Vec2,
     game_phase: GamePhase,
     score: usize,
     line_score: usize,
-    delta_time: std::time::Duration,
+    delta_time: Duration,
+    lock_delay: LockDelay,
+    lock_timer: Duration,
+    spawn_delay: SpawnDelay,
+    // time spent in the current line clear or spawning phase
+    phase_timer: Duration,
+    clearing_rows: Vec<i32>,
+    lock_resets: usize,
+    lowest_row: i32,
+    is_soft_dropping: bool,
+    // used for T-spin detection
+    last_action_was_rotation: bool,
+    // the kick used by the last quarter turn, if the last rotation was one
+    last_kick_index: Option<usize>,
+    // rotation and hold inputs made while waiting for the next block. They only apply if the
+    // block spawns in the next update so the front end has to keep making them for as long as
+    // the key is held.
+    initial_rotation: i32,
+    initial_hold: bool,
+    events: Vec<GameEvent>,
+    combo: usize,
+    back_to_back: bool,
+    mode: GameMode,
+    elapsed_time: Duration,
+    // the elapsed time as each sprint split was reached
+    splits: Vec<Duration>,
 }
 
-impl GameState {
-    pub fn new(
-        board_width: i32,
-        board_height: i32,
-        mut block_type_rng: Box<dyn RangeRng<usize>>,
-    ) -> GameState {
-        let initial_block = Block::random(block_type_rng.as_mut());
-        let max_blocks = (board_width * board_height) as usize;
-        GameState {
+/// Sets up a [`GameState`], optionally partway through a game. Anything which isn't set falls
+/// back to a marathon on an empty board with the guideline lock delay, no spawn delays, a 5 block
+/// next queue, a 7-bag piece generator and the guideline rotation system (SRS).
+///
+/// ```
+/// use tetrust::*;
+///
+/// let game_state = GameStateBuilder::new(10, 20)
+///     .mode(GameMode::sprint())
+///     .active_block(Block::new(BlockType::T), Vec2 { x: 3, y: 0 })
+///     .build();
+/// assert!(game_state.active_block().is_some());
+/// ```
+pub struct GameStateBuilder {
+    board_width: i32,
+    board_height: i32,
+    settled_cells: Vec<Option<BlockType>>,
+    next_queue_len: usize,
+    lock_delay: LockDelay,
+    spawn_delay: SpawnDelay,
+    mode: GameMode,
+    piece_generator: Option<Box<dyn PieceGenerator>>,
+    rotation_system: &'static dyn RotationSystem,
+    active_block: Option<(Block, Vec2)>,
+    score: usize,
+    line_score: usize,
+}
+
+impl GameStateBuilder {
+    pub const STANDARD_BOARD_WIDTH: i32 = 10;
+    pub const STANDARD_BOARD_HEIGHT: i32 = 20;
+    pub const DEFAULT_NEXT_QUEUE_LEN: usize = 5;
+
+    /// The board and rules of the terminal game with the pieces dealt from `seed`. The same mode
+    /// and seed always deal the same pieces.
+    pub fn standard(mode: GameMode, seed: u64) -> Self {
+        Self::new(Self::STANDARD_BOARD_WIDTH, Self::STANDARD_BOARD_HEIGHT)
+            .mode(mode)
+            .spawn_delay(SpawnDelay::standard())
+            .piece_generator(PieceGeneratorType::Bag7.create(Box::new(SeededRangeRng::new(seed))))
+    }
+
+    /// Starts from an empty board of the given size
+    pub fn new(board_width: i32, board_height: i32) -> Self {
+        assert!(board_width > 0 && board_height > 0);
+        GameStateBuilder {
             board_width,
             board_height,
-            block_type_rng,
-            settled_cells: (vec![None; max_blocks]).into_boxed_slice(),
-            next_block: initial_block,
-            active_block: Block::default(), // this block will be immediately replaced
-            active_block_pos: Vec2::zero(),
-            game_phase: GamePhase::StartNextBlock,
+            settled_cells: vec![None; (board_width * board_height) as usize],
+            next_queue_len: Self::DEFAULT_NEXT_QUEUE_LEN,
+            lock_delay: LockDelay::guideline(),
+            spawn_delay: SpawnDelay::instant(),
+            mode: GameMode::Marathon,
+            piece_generator: None,
+            rotation_system: &SRS,
+            active_block: None,
             score: 0,
             line_score: 0,
-            delta_time: std::time::Duration::from_millis(0),
         }
     }
 
-    #[cfg(test)]
-    pub fn make_from_seed(
-        board: &[Vec<bool>],
-        active_block: Block,
-        active_block_pos: Vec2,
-        score: usize,
-        line_score: usize,
-        block_type_rng: Box<dyn RangeRng<usize>>,
-    ) -> Self {
+    /// Starts from a board of settled cells given as rows where `true` is a filled cell
+    pub fn from_board(board: &[Vec<bool>]) -> Self {
         assert!(!board.is_empty());
         let width = board[0].len();
+        assert!(board.iter().all(|row| row.len() == width));
 
-        let max_blocks = board.len() * board[0].len();
+        let mut builder = Self::new(width as i32, board.len() as i32);
+        builder.settled_cells = board
+            .iter()
+            .flat_map(|row| row.iter())
+            .map(|cell| if *cell { Some(BlockType::I) } else { None })
+            .collect();
+        builder
+    }
 
-        let mut settled_cells = vec![None; max_blocks];
-        for (row_index, row) in board.iter().enumerate() {
-            for (col_index, cell) in row.iter().enumerate() {
-                if *cell {
-                    settled_cells[width * row_index + col_index] = Some(BlockType::I);
-                }
-            }
-        }
+    pub fn next_queue_len(mut self, next_queue_len: usize) -> Self {
+        assert!(next_queue_len > 0);
+        self.next_queue_len = next_queue_len;
+        self
+    }
+
+    pub fn lock_delay(mut self, lock_delay: LockDelay) -> Self {
+        self.lock_delay = lock_delay;
+        self
+    }
+
+    pub fn spawn_delay(mut self, spawn_delay: SpawnDelay) -> Self {
+        self.spawn_delay = spawn_delay;
+        self
+    }
+
+    pub fn mode(mut self, mode: GameMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn piece_generator(mut self, piece_generator: Box<dyn PieceGenerator>) -> Self {
+        self.piece_generator = Some(piece_generator);
+        self
+    }
+
+    /// How blocks spawn, turn and kick. Blocks passed to [`GameStateBuilder::active_block`] keep
+    /// their own rotation system.
+    pub fn rotation_system(mut self, rotation_system: &'static dyn RotationSystem) -> Self {
+        self.rotation_system = rotation_system;
+        self
+    }
+
+    /// Starts the game with a block already falling at `pos` instead of spawning one on the first
+    /// update
+    pub fn active_block(mut self, block: Block, pos: Vec2) -> Self {
+        self.active_block = Some((block, pos));
+        self
+    }
+
+    pub fn score(mut self, score: usize) -> Self {
+        self.score = score;
+        self
+    }
+
+    /// How many lines have already been cleared, which also decides the starting level
+    pub fn lines_cleared(mut self, lines_cleared: usize) -> Self {
+        self.line_score = lines_cleared;
+        self
+    }
+
+    pub fn build(self) -> GameState {
+        GameState::new(self)
+    }
+}
+
+impl GameState {
+    const SOFT_DROP_SPEEDUP: u32 = 20;
+    const SOFT_DROP_SCORE_PER_CELL: usize = 1;
+    const HARD_DROP_SCORE_PER_CELL: usize = 2;
+
+    // Only the builder creates games
+    fn new(builder: GameStateBuilder) -> GameState {
+        let mut piece_generator = builder
+            .piece_generator
+            .unwrap_or_else(|| PieceGeneratorType::Bag7.create(Box::new(ThreadRangeRng::new())));
+        let rotation_system = builder.rotation_system;
+        let next_blocks = (0..builder.next_queue_len)
+            .map(|_| Block::spawn(piece_generator.next_piece(), rotation_system))
+            .collect();
+
+        // this block will be immediately replaced if there isn't an active block yet
+        let (active_block, active_block_pos, game_phase) = match builder.active_block {
+            Some((block, pos)) => (block, pos, GamePhase::MoveBlock),
+            None => (
+                Block::spawn(BlockType::I, rotation_system),
+                Vec2::zero(),
+                GamePhase::Spawning,
+            ),
+        };
 
         GameState {
-            board_width: width as i32,
-            board_height: board.len() as i32,
-            block_type_rng,
-            settled_cells: settled_cells.into_boxed_slice(),
-            next_block: Block::default(),
+            board_width: builder.board_width,
+            board_height: builder.board_height,
+            piece_generator,
+            rotation_system,
+            settled_cells: builder.settled_cells.into_boxed_slice(),
+            next_blocks,
+            hold_block: None,
+            can_hold: true,
             active_block,
             active_block_pos,
-            game_phase: GamePhase::MoveBlock,
-            score,
-            line_score,
-            delta_time: std::time::Duration::from_millis(0),
+            game_phase,
+            score: builder.score,
+            line_score: builder.line_score,
+            delta_time: Duration::from_millis(0),
+            lock_delay: builder.lock_delay,
+            lock_timer: Duration::from_millis(0),
+            spawn_delay: builder.spawn_delay,
+            phase_timer: Duration::from_millis(0),
+            clearing_rows: vec![],
+            lock_resets: 0,
+            lowest_row: active_block_pos.y,
+            is_soft_dropping: false,
+            last_action_was_rotation: false,
+            last_kick_index: None,
+            initial_rotation: 0,
+            initial_hold: false,
+            events: vec![],
+            combo: 0,
+            back_to_back: false,
+            mode: builder.mode,
+            elapsed_time: Duration::from_millis(0),
+            splits: vec![],
         }
     }
 
-    #[cfg(test)]
+    /// Width of the board in cells
     pub fn width(&self) -> i32 {
         self.board_width
     }
 
-    #[cfg(test)]
+    /// Height of the board in cells
     pub fn height(&self) -> i32 {
         self.board_height
     }
 
-    pub fn update(&mut self, delta_time: std::time::Duration) {
-        self.add_time(delta_time);
-        while self.consume_next_tick() {
-            match self.game_phase {
-                // Add a new block to the top of the board
-                GamePhase::StartNextBlock => {
-                    let new_next_block = Block::random(self.block_type_rng.as_mut());
-                    let new_active_block = std::mem::replace(&mut self.next_block, new_next_block);
+    /// Advances the game by `delta_time`: spawns blocks, applies gravity, locks landed blocks and
+    /// removes cleared rows
+    pub fn update(&mut self, delta_time: Duration) {
+        // The game clock stops as soon as the game ends
+        if self.is_game_over() {
+            return;
+        }
 
-                    let start_col =
-                        (self.board_width - new_active_block.width()) / 2 - new_active_block.left();
-                    let start_row = -new_active_block.height();
+        self.elapsed_time += delta_time;
+        if let GameMode::Ultra { time_limit } = self.mode {
+            if self.elapsed_time >= time_limit {
+                self.elapsed_time = time_limit;
+                self.game_phase = GamePhase::GameOver(GameOverReason::TimeUp);
+                return;
+            }
+        }
 
-                    let new_active_block_pos = Vec2 {
-                        x: start_col,
-                        y: start_row,
-                    };
+        // Time left over at the end of one phase carries into the next, so the game plays out
+        // the same however the time is split between updates
+        let mut delta_time = delta_time;
 
-                    self.active_block = new_active_block;
-                    self.active_block_pos = new_active_block_pos;
-                    self.game_phase = GamePhase::MoveBlock;
-                }
+        // The time after a block locks counts towards the line clear or entry delay
+        if let Some(time_after_lock) = self.update_lock_delay(delta_time) {
+            delta_time = time_after_lock;
+        }
 
-                // Move the latest block down across the board
-                GamePhase::MoveBlock => {
-                    if self.has_active_block_landed() {
-                        let is_block_above_board = self.active_block_pos.y < 0;
-                        if is_block_above_board {
-                            self.game_phase = GamePhase::GameOver
-                        } else {
-                            // Bake the active block into the settled cell grid.
-                            self.settle_active_block();
-
-                            let num_rows_cleared = self.clear_rows(self.active_block_pos.y);
-                            self.score += Self::calculate_clear_score(num_rows_cleared);
-                            self.line_score += num_rows_cleared;
-
-                            self.game_phase = GamePhase::StartNextBlock
-                        }
-                    } else {
-                        self.move_active_block_down();
+        // Cleared rows are removed once the line clear delay is over
+        if self.game_phase == GamePhase::LineClear {
+            self.phase_timer += delta_time;
+            if self.phase_timer < self.spawn_delay.line_clear {
+                self.forget_spawn_inputs();
+                return;
+            }
+
+            delta_time = self.phase_timer - self.spawn_delay.line_clear;
+            self.remove_clearing_rows();
+            self.start_spawning();
+        }
+
+        // Add a new block to the top of the board once the entry delay is over, holding and
+        // turning it first if the player asked to while waiting for it (IHS and IRS). It starts
+        // falling straight away.
+        if self.game_phase == GamePhase::Spawning {
+            self.phase_timer += delta_time;
+            if self.phase_timer < self.spawn_delay.entry {
+                self.forget_spawn_inputs();
+                return;
+            }
+
+            delta_time = self.phase_timer - self.spawn_delay.entry;
+            let mut new_active_block = self.take_next_block();
+            if std::mem::take(&mut self.initial_hold) {
+                new_active_block = self.swap_hold_block(new_active_block);
+                self.can_hold = false;
+            }
+
+            let initial_rotation = std::mem::take(&mut self.initial_rotation);
+            self.spawn_active_block(new_active_block, initial_rotation);
+            self.delta_time = Duration::from_millis(0);
+        }
+
+        // Move the latest block down across the board. Once it has landed it stays put until the
+        // lock delay expires.
+        if self.game_phase == GamePhase::MoveBlock {
+            self.add_time(delta_time);
+            while self.consume_next_tick() {
+                if !self.has_active_block_landed() {
+                    self.move_active_block_down();
+                    if self.is_soft_dropping {
+                        self.score += Self::SOFT_DROP_SCORE_PER_CELL;
                     }
                 }
-
-                // The game is over; NOOP
-                GamePhase::GameOver => (),
             }
         }
     }
 
+    /// Shifts the active block left (negative) or right (positive) if nothing is in the way
     pub fn move_active_block_horizontal(&mut self, horizontal_motion: i32) {
         match self.game_phase {
             GamePhase::MoveBlock => {
                 if self.can_active_block_move(horizontal_motion) {
                     self.active_block_pos.x += horizontal_motion;
+                    self.last_action_was_rotation = false;
+                    self.reset_lock_delay();
+                }
+            }
+            GamePhase::Spawning | GamePhase::LineClear | GamePhase::GameOver(_) => (),
+        }
+    }
+
+    /// Shifts the active block left (negative) or right (positive) until something is in the way
+    pub fn shift_active_block_to_wall(&mut self, direction: i32) {
+        match self.game_phase {
+            GamePhase::MoveBlock => {
+                let direction = direction.signum();
+                if self.can_active_block_move(direction) {
+                    while self.can_active_block_move(direction) {
+                        self.active_block_pos.x += direction;
+                    }
+                    self.last_action_was_rotation = false;
+                    self.reset_lock_delay();
                 }
             }
-            GamePhase::StartNextBlock | GamePhase::GameOver => (),
+            GamePhase::Spawning | GamePhase::LineClear | GamePhase::GameOver(_) => (),
         }
     }
 
+    /// Moves the active block down a row without checking for collisions
     pub fn move_active_block_down(&mut self) {
         self.active_block_pos.y += 1;
+        self.last_action_was_rotation = false;
+
+        // Reaching a new lowest row earns back all of the lock delay resets
+        if self.active_block_pos.y > self.lowest_row {
+            self.lowest_row = self.active_block_pos.y;
+            self.lock_resets = 0;
+        }
     }
 
+    /// Moves the active block down until it lands without locking it
     pub fn quick_drop(&mut self) {
         while !self.has_active_block_landed() {
             self.move_active_block_down();
         }
     }
 
-    pub fn rotate_block(&mut self, relative_rotation: i32) {
+    /// Speeds up gravity for as long as soft drop is held
+    pub fn set_soft_drop(&mut self, is_soft_dropping: bool) {
+        self.is_soft_dropping = is_soft_dropping;
+    }
+
+    /// Drops the active block straight to the bottom and locks it in place without any lock delay
+    pub fn hard_drop(&mut self) {
+        match self.game_phase {
+            GamePhase::MoveBlock => {
+                let start_row = self.active_block_pos.y;
+                self.quick_drop();
+                let cells_dropped = (self.active_block_pos.y - start_row) as usize;
+                self.score += cells_dropped * Self::HARD_DROP_SCORE_PER_CELL;
+
+                self.lock_active_block();
+            }
+            GamePhase::Spawning | GamePhase::LineClear | GamePhase::GameOver(_) => (),
+        }
+    }
+
+    /// Rotates the active block left (-1), right (1) or halfway round (2 or -2), kicking it out of
+    /// the way of walls and settled blocks if needed. Any other rotation is an error. While
+    /// waiting for the next block the block spawns turned, if it fits, when it spawns in the next
+    /// update (IRS). Rotations which don't lead straight into a spawn are forgotten.
+    pub fn rotate_block(&mut self, relative_rotation: i32) -> Result<(), String> {
+        let rotated_block = self.active_block.rotate(relative_rotation)?;
+
         // no rotation means no rotation. noop.
         if relative_rotation == 0 {
-            return;
+            return Ok(());
         }
 
         match self.game_phase {
@@ -184,46 +622,180 @@ impl GameState {
 
                 // O blocks can always rotate since rotating doesn't actually change their shape.
                 if active_block.block_type == BlockType::O {
-                    return;
+                    return Ok(());
                 }
 
-                let maybe_rotated_block = self.try_rotate_active_block(relative_rotation);
-                if let Some((rotated_block, kicked_pos)) = maybe_rotated_block {
+                if let Some((kicked_pos, kick_index)) = self.try_rotate_active_block(rotated_block)
+                {
                     self.active_block = rotated_block;
                     self.active_block_pos = kicked_pos;
+                    self.last_action_was_rotation = true;
+                    // Only quarter turn kicks count towards a full T-spin
+                    self.last_kick_index = if relative_rotation.abs() == 1 {
+                        Some(kick_index)
+                    } else {
+                        None
+                    };
+                    self.reset_lock_delay();
                 }
             }
-            GamePhase::StartNextBlock | GamePhase::GameOver => (),
+            GamePhase::Spawning | GamePhase::LineClear => self.initial_rotation = relative_rotation,
+            GamePhase::GameOver(_) => (),
+        }
+
+        Ok(())
+    }
+
+    /// Swaps the active block with the held block, or with the next block if nothing is held yet.
+    /// While waiting for the next block, the next block goes straight into the hold slot if it
+    /// spawns in the next update (IHS). Holds which don't lead straight into a spawn are
+    /// forgotten.
+    pub fn hold(&mut self) {
+        // A piece may only be held once between each time a piece locks
+        if !self.can_hold {
+            return;
+        }
+
+        match self.game_phase {
+            GamePhase::MoveBlock => {
+                let new_active_block = self.swap_hold_block(self.active_block);
+                self.spawn_active_block(new_active_block, 0);
+                self.can_hold = false;
+            }
+            GamePhase::Spawning | GamePhase::LineClear => self.initial_hold = true,
+            GamePhase::GameOver(_) => (),
         }
     }
 
+    /// The block in the hold slot, if any
+    pub fn hold_block(&self) -> Option<Block> {
+        self.hold_block
+    }
+
+    /// Whether the hold slot can be used before the active block locks
+    pub fn can_hold(&self) -> bool {
+        self.can_hold
+    }
+
+    /// The block which spawns next
     pub fn preview_block(&self) -> Block {
-        self.next_block
+        self.next_blocks[0]
+    }
+
+    /// Returns up to `count` of the upcoming blocks in the order they will be played
+    pub fn preview_blocks(&self, count: usize) -> Vec<Block> {
+        self.next_blocks.iter().take(count).copied().collect()
     }
 
+    /// How many upcoming blocks are queued
+    pub fn next_queue_len(&self) -> usize {
+        self.next_blocks.len()
+    }
+
+    /// The falling block and its position, or `None` while waiting for the next block to spawn
     // TODO: maybe active_block should actually be represented by an option and force the unwrap check in places
     pub fn active_block(&self) -> Option<(Block, Vec2)> {
-        // If we are in the "StartNextBlock" phase it means that we've just placed our previous active block
-        if self.game_phase == GamePhase::StartNextBlock {
-            None
-        } else {
-            Some((self.active_block, self.active_block_pos))
+        // While clearing lines or spawning, the previous active block has already been placed
+        match self.game_phase {
+            GamePhase::Spawning | GamePhase::LineClear => None,
+            GamePhase::MoveBlock | GamePhase::GameOver(_) => {
+                Some((self.active_block, self.active_block_pos))
+            }
+        }
+    }
+
+    /// The full rows which are still on the board during the line clear delay, top to bottom
+    pub fn clearing_rows(&self) -> &[i32] {
+        &self.clearing_rows
+    }
+
+    /// How far through the line clear delay the game is, from 0 to 1. It's 0 when no lines are
+    /// being cleared.
+    pub fn line_clear_progress(&self) -> f32 {
+        if self.game_phase != GamePhase::LineClear {
+            return 0.0;
         }
+
+        let progress = self.phase_timer.as_secs_f32() / self.spawn_delay.line_clear.as_secs_f32();
+        progress.min(1.0)
+    }
+
+    /// Whether a block fits at a position: every cell is inside the walls, above the floor and
+    /// not on a settled cell. Cells above the top of the board are open.
+    pub fn is_valid_position(&self, block: Block, pos: Vec2) -> bool {
+        block
+            .cells()
+            .iter()
+            .all(|cell| !self.is_cell_occupied(pos.x + cell.x, pos.y + cell.y))
+    }
+
+    /// Drains the events which have happened since the last time this was called
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
     }
 
     pub fn is_game_over(&self) -> bool {
-        self.game_phase == GamePhase::GameOver
+        self.game_over_reason().is_some()
+    }
+
+    /// Why the game ended or `None` if it's still being played
+    pub fn game_over_reason(&self) -> Option<GameOverReason> {
+        match self.game_phase {
+            GamePhase::GameOver(reason) => Some(reason),
+            GamePhase::Spawning | GamePhase::MoveBlock | GamePhase::LineClear => None,
+        }
     }
 
     pub fn score(&self) -> usize {
         self.score
     }
 
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    /// Total number of lines cleared this game
+    pub fn lines_cleared(&self) -> usize {
+        self.line_score
+    }
+
+    /// How long the game has been running, not counting time spent paused
+    pub fn elapsed_time(&self) -> Duration {
+        self.elapsed_time
+    }
+
+    /// The elapsed time as every [`GameMode::SPRINT_SPLIT_INTERVAL`] lines were cleared in a
+    /// sprint, oldest first. A clear which passes more than one split records the same time for
+    /// each of them.
+    pub fn splits(&self) -> &[Duration] {
+        &self.splits
+    }
+
+    /// How much time is left in timed modes
+    pub fn remaining_time(&self) -> Option<Duration> {
+        match self.mode {
+            GameMode::Marathon | GameMode::Sprint { .. } => None,
+            GameMode::Ultra { time_limit } => Some(time_limit - self.elapsed_time),
+        }
+    }
+
+    /// How many blocks in a row have cleared lines
+    pub fn combo(&self) -> usize {
+        self.combo
+    }
+
+    /// Whether the last line clear was difficult so the next difficult clear will be a back-to-back
+    pub fn back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    /// The current level, starting at 1 and increasing every 5 lines
     pub fn level(&self) -> usize {
         // each level is cleared by clearing 5 lines
         (self.line_score / 5) + 1
     }
 
+    /// Calls `op` with the type and position of every cell which has locked onto the board
     pub fn for_each_settled_piece<F>(&self, mut op: F)
     where
         F: FnMut(BlockType, Vec2),
@@ -237,19 +809,24 @@ impl GameState {
         }
     }
 
-    #[cfg(test)]
+    /// How many cells of the board are filled
     pub fn get_settled_piece_count(&self) -> usize {
         self.settled_cells.iter().filter(|c| c.is_some()).count()
     }
 
-    fn add_time(&mut self, delta_time: std::time::Duration) {
+    fn add_time(&mut self, delta_time: Duration) {
         self.delta_time += delta_time
     }
 
-    fn get_move_period(&self) -> std::time::Duration {
+    fn get_move_period(&self) -> Duration {
         // TODO: should this difficulty ramp be hand-tuned?
         let clamped_level_index = std::cmp::min(self.level() - 1, 10) as u64;
-        std::time::Duration::from_millis(250 - (15 * clamped_level_index))
+        let move_period = Duration::from_millis(250 - (15 * clamped_level_index));
+        if self.is_soft_dropping {
+            move_period / Self::SOFT_DROP_SPEEDUP
+        } else {
+            move_period
+        }
     }
 
     fn consume_next_tick(&mut self) -> bool {
@@ -262,107 +839,267 @@ impl GameState {
         false
     }
 
-    fn cell_index(&self, x: i32, y: i32) -> usize {
-        (self.board_width * y + x) as usize
+    fn take_next_block(&mut self) -> Block {
+        let new_next_block = Block::spawn(self.piece_generator.next_piece(), self.rotation_system);
+        self.next_blocks.push_back(new_next_block);
+        // NOTE: Unwrap is safe because the queue is refilled before being drained
+        self.next_blocks.pop_front().unwrap()
     }
 
-    fn can_active_block_move(&self, horizontal_motion: i32) -> bool {
-        if horizontal_motion == 0 {
-            return false;
+    // Puts a block into the hold slot in its spawn orientation and returns the block which was
+    // held, or the next block if nothing was
+    fn swap_hold_block(&mut self, block: Block) -> Block {
+        let block_to_hold = Block::spawn(block.block_type, self.rotation_system);
+        match self.hold_block.replace(block_to_hold) {
+            Some(held_block) => held_block,
+            None => self.take_next_block(),
         }
+    }
+
+    // Place a new active block centered just above the top of the board. An initial rotation
+    // only applies if the turned block fits where it spawns, so turning can dodge a top out.
+    fn spawn_active_block(&mut self, new_active_block: Block, initial_rotation: i32) {
+        let start_col = (self.board_width - new_active_block.width()) / 2 - new_active_block.left();
+        let start_row = -new_active_block.height();
+
+        let new_active_block_pos = Vec2 {
+            x: start_col,
+            y: start_row,
+        };
+
+        let new_active_block = match new_active_block.rotate(initial_rotation) {
+            Ok(rotated_block) if self.is_valid_position(rotated_block, new_active_block_pos) => {
+                rotated_block
+            }
+            _ => new_active_block,
+        };
+
+        self.active_block = new_active_block;
+        self.active_block_pos = new_active_block_pos;
+        self.last_action_was_rotation = false;
 
-        let wall_to_check = if horizontal_motion < 0 {
-            self.left_wall()
+        // A block with no room to spawn tops out the game. Blocks spawn `height()` rows above the
+        // board, so only blocks whose cells start below the top of their bounding box (the flat
+        // I, or a block turned by IRS) reach into the board and can be blocked here.
+        self.game_phase = if self.is_valid_position(new_active_block, new_active_block_pos) {
+            GamePhase::MoveBlock
         } else {
-            self.right_wall()
+            GamePhase::GameOver(GameOverReason::ToppedOut)
         };
 
-        let is_touching_wall =
-            is_touching_bound(self.active_block, self.active_block_pos, wall_to_check);
+        self.lock_timer = Duration::from_millis(0);
+        self.lock_resets = 0;
+        self.lowest_row = new_active_block_pos.y;
+    }
 
-        if is_touching_wall {
-            return false;
+    // Returns whether the active block was locked in place
+    // Returns how much of the update was left when the block locked, or None if it didn't
+    fn update_lock_delay(&mut self, delta_time: Duration) -> Option<Duration> {
+        if self.game_phase != GamePhase::MoveBlock {
+            return None;
         }
 
-        let motion_vec = Vec2 {
-            x: horizontal_motion,
-            y: 0,
+        // The lock delay only runs while the block is resting on something. Time only counts
+        // towards locking if the block was already resting when the update started.
+        if !self.has_active_block_landed() {
+            self.lock_timer = Duration::from_millis(0);
+            return None;
+        }
+
+        self.lock_timer += delta_time;
+
+        let are_resets_exhausted = match self.lock_delay.max_resets {
+            Some(max_resets) => self.lock_resets >= max_resets,
+            None => false,
         };
 
-        let will_block_collide = self.does_block_collide_with_settled_blocks(
-            self.active_block,
-            self.active_block_pos,
-            motion_vec,
-        );
+        // Running out of resets locks the block as soon as the update starts
+        let time_after_lock = if are_resets_exhausted {
+            Some(delta_time)
+        } else {
+            self.lock_timer.checked_sub(self.lock_delay.duration)
+        };
 
-        !will_block_collide
-    }
+        if time_after_lock.is_some() {
+            self.lock_active_block();
+        }
 
-    fn left_wall(&self) -> Bound {
-        Bound::LeftWall(-1)
+        time_after_lock
     }
 
-    fn right_wall(&self) -> Bound {
-        Bound::RightWall(self.board_width)
-    }
+    fn reset_lock_delay(&mut self) {
+        let can_reset = match self.lock_delay.max_resets {
+            Some(max_resets) => self.lock_resets < max_resets,
+            None => true,
+        };
 
-    fn floor(&self) -> Bound {
-        Bound::Floor(self.board_height)
+        // Only moves made while the lock delay is running count against the reset limit
+        let is_lock_delay_running =
+            self.lock_timer > Duration::from_millis(0) || self.has_active_block_landed();
+
+        if can_reset && is_lock_delay_running {
+            self.lock_timer = Duration::from_millis(0);
+            self.lock_resets += 1;
+        }
     }
 
-    fn try_rotate_active_block(&self, relative_rotation: i32) -> Option<(Block, Vec2)> {
-        let original_block = self.active_block;
-        let original_block_pos = self.active_block_pos;
+    fn lock_active_block(&mut self) {
+        // Only the block's cells count, not the empty rows at the top of its bounding box
+        let is_block_above_board = self.active_block_pos.y + self.active_block.top() < 0;
+        if is_block_above_board {
+            self.game_phase = GamePhase::GameOver(GameOverReason::ToppedOut)
+        } else {
+            let t_spin = self.detect_t_spin();
+
+            // Bake the active block into the settled cell grid.
+            self.settle_active_block();
+
+            self.clearing_rows = self.find_full_rows();
+            let num_rows_cleared = self.clearing_rows.len();
+            let level = self.level();
+            let splits_passed = (self.line_score + num_rows_cleared)
+                / GameMode::SPRINT_SPLIT_INTERVAL
+                - self.line_score / GameMode::SPRINT_SPLIT_INTERVAL;
+            self.line_score += num_rows_cleared;
+            if let GameMode::Sprint { .. } = self.mode {
+                for _ in 0..splits_passed {
+                    self.splits.push(self.elapsed_time);
+                }
+            }
 
-        let rotated_block = original_block.rotate(relative_rotation);
-        let kicks = original_block
-            .rot
-            .get_kick_attempts(original_block.block_type, rotated_block.rot);
+            // Any block which doesn't clear a line breaks the combo
+            self.combo = if num_rows_cleared > 0 {
+                self.combo + 1
+            } else {
+                0
+            };
 
-        for kick in &kicks {
-            let kicked_block_pos = Vec2 {
-                x: original_block_pos.x + kick.x,
-                y: original_block_pos.y + kick.y,
+            let mut line_clear = LineClear {
+                lines: num_rows_cleared,
+                t_spin,
+                back_to_back: false,
+                combo: self.combo,
+                score: Self::calculate_clear_score(num_rows_cleared, t_spin) * level,
             };
 
-            let does_block_collide_after_kick = self.does_block_collide_with_settled_blocks(
-                rotated_block,
-                kicked_block_pos,
-                Vec2::zero(),
-            );
+            // Only clears which actually clear lines affect the back-to-back chain
+            if num_rows_cleared > 0 {
+                let is_difficult = line_clear.is_difficult();
+                if is_difficult && self.back_to_back {
+                    line_clear.back_to_back = true;
+                    line_clear.score = line_clear.score * 3 / 2;
+                }
+                self.back_to_back = is_difficult;
+            }
+
+            line_clear.score += Self::calculate_combo_score(self.combo) * level;
+            self.score += line_clear.score;
 
-            if does_block_collide_after_kick {
-                continue;
+            if num_rows_cleared > 0 || t_spin != TSpin::None {
+                self.events.push(GameEvent::LineClear(line_clear));
             }
 
-            if is_touching_bound(rotated_block, kicked_block_pos, self.floor())
-                || is_touching_bound(rotated_block, kicked_block_pos, self.left_wall())
-                || is_touching_bound(rotated_block, kicked_block_pos, self.right_wall())
+            // Cleared rows are still on the board so check whether everything else is empty
+            let is_perfect_clear = num_rows_cleared > 0
+                && (0..self.board_height)
+                    .filter(|row| !self.clearing_rows.contains(row))
+                    .all(|row| {
+                        (0..self.board_width)
+                            .all(|col| self.settled_cells[self.cell_index(col, row)].is_none())
+                    });
+            if is_perfect_clear {
+                let bonus = Self::calculate_perfect_clear_score(num_rows_cleared) * level;
+                self.score += bonus;
+                self.events.push(GameEvent::PerfectClear {
+                    lines: num_rows_cleared,
+                    score: bonus,
+                });
+            }
+
+            self.can_hold = true;
+            let is_goal_complete = match self.mode {
+                GameMode::Sprint { line_goal } => self.line_score >= line_goal,
+                GameMode::Marathon | GameMode::Ultra { .. } => false,
+            };
+
+            // The game ends with the cleared rows already gone
+            if is_goal_complete {
+                self.remove_clearing_rows();
+                self.game_phase = GamePhase::GameOver(GameOverReason::GoalComplete);
+            } else if num_rows_cleared > 0 && self.spawn_delay.line_clear > Duration::from_millis(0)
             {
-                continue;
+                self.game_phase = GamePhase::LineClear;
+                self.phase_timer = Duration::from_millis(0);
+            } else {
+                self.remove_clearing_rows();
+                self.start_spawning();
             }
+        }
+    }
+
+    // IRS and IHS only use the inputs made just before the block spawns, so a tap while waiting
+    // doesn't carry over to it
+    fn forget_spawn_inputs(&mut self) {
+        self.initial_rotation = 0;
+        self.initial_hold = false;
+    }
+
+    fn start_spawning(&mut self) {
+        self.game_phase = GamePhase::Spawning;
+        self.phase_timer = Duration::from_millis(0);
+    }
+
+    fn cell_index(&self, x: i32, y: i32) -> usize {
+        (self.board_width * y + x) as usize
+    }
 
-            return Some((rotated_block, kicked_block_pos));
+    fn can_active_block_move(&self, horizontal_motion: i32) -> bool {
+        if horizontal_motion == 0 {
+            return false;
         }
 
-        None
+        let moved_block_pos = Vec2 {
+            x: self.active_block_pos.x + horizontal_motion,
+            y: self.active_block_pos.y,
+        };
+        self.is_valid_position(self.active_block, moved_block_pos)
     }
 
-    fn has_active_block_landed(&self) -> bool {
-        let is_touching_floor =
-            is_touching_bound(self.active_block, self.active_block_pos, self.floor());
+    // Returns the rotated block's kicked position and which kick attempt succeeded
+    fn try_rotate_active_block(&self, rotated_block: Block) -> Option<(Vec2, usize)> {
+        let original_block = self.active_block;
+        let original_block_pos = self.active_block_pos;
 
-        if is_touching_floor {
-            return true;
+        let kicks =
+            original_block
+                .rotation_system
+                .kicks(original_block, rotated_block.rot, &|cell| {
+                    self.is_cell_occupied(
+                        original_block_pos.x + cell.x,
+                        original_block_pos.y + cell.y,
+                    )
+                });
+
+        for (kick_index, kick) in kicks.iter().enumerate() {
+            let kicked_block_pos = Vec2 {
+                x: original_block_pos.x + kick.x,
+                y: original_block_pos.y + kick.y,
+            };
+
+            if self.is_valid_position(rotated_block, kicked_block_pos) {
+                return Some((kicked_block_pos, kick_index));
+            }
         }
 
-        let does_block_collide_below = self.does_block_collide_with_settled_blocks(
-            self.active_block,
-            self.active_block_pos,
-            Vec2 { x: 0, y: 1 },
-        );
+        None
+    }
 
-        does_block_collide_below
+    fn has_active_block_landed(&self) -> bool {
+        let block_pos_below = Vec2 {
+            x: self.active_block_pos.x,
+            y: self.active_block_pos.y + 1,
+        };
+        !self.is_valid_position(self.active_block, block_pos_below)
     }
 
     fn settle_active_block(&mut self) {
@@ -377,39 +1114,37 @@ impl GameState {
         }
     }
 
-    fn clear_rows(&mut self, start_row: i32) -> usize {
-        let mut rows_to_check = [false, false, false, false];
-        for cell in &self.active_block.cells() {
-            rows_to_check[cell.y as usize] = true;
-        }
-
-        let mut rows_cleared = 0;
-        for row in rows_to_check
+    // The rows the active block touches which are now full, top to bottom
+    fn find_full_rows(&self) -> Vec<i32> {
+        let mut rows_to_check: Vec<i32> = self
+            .active_block
+            .cells()
             .iter()
-            .enumerate()
-            .filter(|(_, check_row)| **check_row)
-            .map(|(i, _)| i as i32 + start_row)
-        {
-            let row_cleared = self.try_clear_row(row);
-            if row_cleared {
-                rows_cleared += 1;
-            }
-        }
+            .map(|cell| cell.y + self.active_block_pos.y)
+            .collect();
+        rows_to_check.sort_unstable();
+        rows_to_check.dedup();
+
+        rows_to_check
+            .into_iter()
+            .filter(|row| {
+                (0..self.board_width)
+                    .all(|col| self.settled_cells[self.cell_index(col, *row)].is_some())
+            })
+            .collect()
+    }
 
-        rows_cleared
+    // Removes the cleared rows and drops everything above them down. Going top to bottom means
+    // removing a row never moves the rows still to be removed.
+    fn remove_clearing_rows(&mut self) {
+        for row in std::mem::take(&mut self.clearing_rows) {
+            self.remove_row(row);
+        }
     }
 
-    fn try_clear_row(&mut self, row: i32) -> bool {
+    fn remove_row(&mut self, row: i32) {
         let row_start = self.cell_index(0, row);
         let row_end = self.cell_index(self.board_width, row);
-        let cells_in_row_count = self.settled_cells[row_start..row_end]
-            .iter()
-            .filter(|c| c.is_some())
-            .count();
-
-        if cells_in_row_count != self.board_width as usize {
-            return false;
-        }
 
         // Clear out the row
         for cell in self.settled_cells[row_start..row_end].iter_mut() {
@@ -426,50 +1161,138 @@ impl GameState {
                     .swap(preceding_row_cell_index, next_row_cell_index);
             }
         }
+    }
+
+    // The guideline scoring table before being multiplied by the level
+    fn calculate_clear_score(num_cleared_lines: usize, t_spin: TSpin) -> usize {
+        if num_cleared_lines > 4 {
+            panic!("There is no way to clear more than 4 lines at once!");
+        }
 
-        true
+        match (t_spin, num_cleared_lines) {
+            (TSpin::None, 0) => 0,
+            (TSpin::None, 1) => 100,
+            (TSpin::None, 2) => 300,
+            (TSpin::None, 3) => 500,
+            (TSpin::None, _) => 800,
+            (TSpin::Mini, 0) => 100,
+            (TSpin::Mini, 1) => 200,
+            (TSpin::Mini, _) => 400,
+            (TSpin::Full, 0) => 400,
+            (TSpin::Full, 1) => 800,
+            (TSpin::Full, 2) => 1200,
+            (TSpin::Full, _) => 1600,
+        }
     }
 
-    fn calculate_clear_score(num_cleared_lines: usize) -> usize {
-        match num_cleared_lines {
+    // The first clear in a chain starts the combo. Every clear after that earns a bonus.
+    fn calculate_combo_score(combo: usize) -> usize {
+        50 * combo.saturating_sub(1)
+    }
+
+    // Bonus on top of the line clear score for emptying the whole board
+    fn calculate_perfect_clear_score(num_rows_cleared: usize) -> usize {
+        match num_rows_cleared {
             0 => 0,
-            1 => 40,
-            2 => 100,
-            3 => 300,
-            4 => 1200,
-            _ => panic!("There is no way to clear more than 4 lines at once!"),
-        }
-    }
-
-    fn does_block_collide_with_settled_blocks(
-        &self,
-        block: Block,
-        block_pos: Vec2,
-        move_vector: Vec2,
-    ) -> bool {
-        let moved_block_cells = translate_cells(
-            &block.cells(),
-            block_pos.y + move_vector.y,
-            block_pos.x + move_vector.x,
-        );
-
-        fn in_ex_range(v: i32, lower: i32, upper: i32) -> bool {
-            v >= lower && v < upper
-        }
-        for moved_block_cell in &moved_block_cells {
-            if !in_ex_range(moved_block_cell.x, 0, self.board_width)
-                || !in_ex_range(moved_block_cell.y, 0, self.board_height)
-            {
-                continue;
-            }
+            1 => 800,
+            2 => 1200,
+            3 => 1800,
+            4 => 2000,
+            _ => panic!(
+                "Unexpected perfect clear with {} rows cleared",
+                num_rows_cleared
+            ),
+        }
+    }
 
-            if self.settled_cells[self.cell_index(moved_block_cell.x, moved_block_cell.y)].is_some()
-            {
-                return true;
-            }
+    // A T block locked in place right after rotating is a T-spin if at least 3 of the 4 corners
+    // around its center are occupied. It is only a full T-spin if both corners on the side the T
+    // points towards are occupied, or if it got there with the last (and largest) kick.
+    fn detect_t_spin(&self) -> TSpin {
+        if self.active_block.block_type != BlockType::T || !self.last_action_was_rotation {
+            return TSpin::None;
         }
 
-        false
+        // the T block's center is the cell touching the other three, and it points the way its
+        // three arms add up to (the two opposite arms cancel out)
+        let cells = self.active_block.cells();
+        let is_adjacent = |a: &Vec2, b: &Vec2| (a.x - b.x).abs() + (a.y - b.y).abs() == 1;
+        // NOTE: Unwrap is safe because every T layout has a cell touching the other three
+        let center = *cells
+            .iter()
+            .find(|cell| {
+                cells
+                    .iter()
+                    .filter(|other| is_adjacent(cell, other))
+                    .count()
+                    == 3
+            })
+            .unwrap();
+        let facing = cells.iter().fold(Vec2::zero(), |facing, cell| Vec2 {
+            x: facing.x + cell.x - center.x,
+            y: facing.y + cell.y - center.y,
+        });
+
+        let is_corner_occupied = |corner: Vec2| {
+            self.is_cell_occupied(
+                self.active_block_pos.x + center.x + corner.x,
+                self.active_block_pos.y + center.y + corner.y,
+            )
+        };
+
+        let corners = [
+            Vec2 { x: -1, y: -1 },
+            Vec2 { x: 1, y: -1 },
+            Vec2 { x: -1, y: 1 },
+            Vec2 { x: 1, y: 1 },
+        ];
+        let occupied_corner_count = corners
+            .iter()
+            .filter(|corner| is_corner_occupied(**corner))
+            .count();
+        if occupied_corner_count < 3 {
+            return TSpin::None;
+        }
+
+        // the front corners are on the side the T points towards
+        let front_corners = [
+            Vec2 {
+                x: facing.x + facing.y,
+                y: facing.y + facing.x,
+            },
+            Vec2 {
+                x: facing.x - facing.y,
+                y: facing.y - facing.x,
+            },
+        ];
+
+        let is_full_t_spin_kick = self.last_kick_index.is_some_and(|kick_index| {
+            self.active_block
+                .rotation_system
+                .is_full_t_spin_kick(kick_index)
+        });
+        if front_corners
+            .iter()
+            .all(|corner| is_corner_occupied(*corner))
+            || is_full_t_spin_kick
+        {
+            TSpin::Full
+        } else {
+            TSpin::Mini
+        }
+    }
+
+    // Walls and the floor count as occupied; the open space above the board does not
+    fn is_cell_occupied(&self, x: i32, y: i32) -> bool {
+        if x < 0 || x >= self.board_width || y >= self.board_height {
+            return true;
+        }
+
+        if y < 0 {
+            return false;
+        }
+
+        self.settled_cells[self.cell_index(x, y)].is_some()
     }
 }
 
@@ -482,11 +1305,3 @@ fn translate_cells(cells: &[Vec2; 4], row_translation: i32, col_translation: i32
 
     translated_cells
 }
-
-fn is_touching_bound(block: Block, block_pos: Vec2, bound: Bound) -> bool {
-    match bound {
-        Bound::Floor(floor) => block.top() + block_pos.y + block.height() >= floor,
-        Bound::LeftWall(left) => block.left() + block_pos.x <= left + 1,
-        Bound::RightWall(right) => block.left() + block_pos.x + block.width() >= right,
-    }
-}
@@ -0,0 +1,340 @@
+use crate::action::*;
+use crate::config::*;
+use crate::keybindings::*;
+use std::time::Duration;
+
+/// How held keys repeat. Every setting is in milliseconds in the `[handling]` table of a config
+/// file:
+///
+/// ```text
+/// [handling]
+/// das = 133
+/// # shift straight to the wall once DAS charges
+/// arr = 0
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handling {
+    /// delayed auto shift: how long a direction is held before it starts repeating
+    pub das: Duration,
+    /// auto repeat rate: the time between shifts once DAS has charged. Zero shifts the block
+    /// straight to the wall.
+    pub arr: Duration,
+    /// how long the terminal waits before it starts repeating a held key
+    pub key_repeat_delay: Duration,
+    /// the longest gap between the terminal's repeats of a held key before it counts as released
+    pub key_release_time: Duration,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das: Duration::from_millis(167),
+            arr: Duration::from_millis(33),
+            key_repeat_delay: Duration::from_millis(500),
+            key_release_time: Duration::from_millis(75),
+        }
+    }
+}
+
+impl Handling {
+    /// Reads the `[handling]` table of a config file, starting from the defaults
+    pub fn parse<S: AsRef<str>>(config: S) -> Result<Self, String> {
+        let mut handling = Handling::default();
+        parse_table(config.as_ref(), "handling", |name, value| {
+            let setting = match name {
+                "das" => &mut handling.das,
+                "arr" => &mut handling.arr,
+                "key_repeat_delay" => &mut handling.key_repeat_delay,
+                "key_release_time" => &mut handling.key_release_time,
+                _ => return Err(format!("unknown handling setting '{}'", name)),
+            };
+
+            let millis = value
+                .parse::<u64>()
+                .map_err(|e| format!("bad number of milliseconds '{}' ({})", value, e))?;
+            *setting = Duration::from_millis(millis);
+            Ok(())
+        })?;
+
+        if handling.key_release_time == Duration::from_millis(0) {
+            return Err(String::from("key_release_time must be more than 0"));
+        }
+
+        Ok(handling)
+    }
+
+    pub fn to_config_string(&self) -> String {
+        format!(
+            "[handling]\ndas = {}\narr = {}\nkey_repeat_delay = {}\nkey_release_time = {}\n",
+            self.das.as_millis(),
+            self.arr.as_millis(),
+            self.key_repeat_delay.as_millis(),
+            self.key_release_time.as_millis()
+        )
+    }
+}
+
+// What's known about a key from the presses and repeats the terminal has reported for it
+#[derive(Debug, Clone, Copy)]
+struct HeldKey {
+    // time since the key was pressed
+    held_time: Duration,
+    // time since the last press or repeat
+    idle_time: Duration,
+    // the terminal only repeats keys which are still held down
+    is_repeating: bool,
+    // whether a hard drop was pressed after this key, locking the block the key was meant for
+    is_before_hard_drop: bool,
+}
+
+// The direction being auto shifted
+#[derive(Debug, Clone, Copy)]
+struct Shift {
+    direction: Control,
+    // time since the direction started shifting, which charges DAS
+    time: Duration,
+    // time since DAS charged and the direction started repeating
+    charged_time: Option<Duration>,
+    // how many times the direction has repeated since DAS charged
+    repeats: u32,
+    // whether an ARR of 0 has shifted the block to the wall since it last moved some other way
+    is_at_wall: bool,
+}
+
+/// Turns key presses into game actions with DAS and ARR. Terminals don't report key releases,
+/// only a press followed by a stream of repeats while the key is held, so a key counts as held
+/// from its press until the terminal stops repeating it.
+pub struct InputHandler {
+    handling: Handling,
+    // indexed the same as Control::ALL
+    held_keys: Vec<Option<HeldKey>>,
+    pending_presses: Vec<Control>,
+    shift: Option<Shift>,
+    is_soft_dropping: bool,
+}
+
+impl InputHandler {
+    pub fn new(handling: Handling) -> Self {
+        InputHandler {
+            handling,
+            held_keys: vec![None; Control::ALL.len()],
+            pending_presses: vec![],
+            shift: None,
+            is_soft_dropping: false,
+        }
+    }
+
+    /// Tells the handler the block has changed or moved other than by shifting, because it
+    /// spawned or the shifts were thrown away. A charged direction with an ARR of 0 shifts it to
+    /// the wall again.
+    pub fn block_changed(&mut self) {
+        if let Some(shift) = &mut self.shift {
+            shift.is_at_wall = false;
+        }
+    }
+
+    /// Records a press, or a repeat, of a control's key. It's handled on the next update.
+    pub fn press(&mut self, control: Control) {
+        self.pending_presses.push(control);
+    }
+
+    /// Whether a control's key is currently taken to be held down
+    pub fn is_held(&self, control: Control) -> bool {
+        self.held_keys[control.index()].is_some()
+    }
+
+    /// The rotate and hold actions for the keys being held down right now. Applied while the game
+    /// is waiting for the next block they turn or hold it as it spawns (IRS and IHS).
+    pub fn held_spawn_actions(&self) -> Vec<Action> {
+        // Terminals can't tell a tap from a key held down until they start repeating it, and
+        // that can take longer than the wait for the next block. So keys count from their press
+        // unless a hard drop came after it, in which case the press was a tap for the block which
+        // was dropped and only counts once the terminal shows it's still held by repeating it.
+        [
+            (Control::RotateLeft, Action::RotateLeft),
+            (Control::RotateRight, Action::RotateRight),
+            (Control::Rotate180, Action::Rotate180),
+            (Control::Hold, Action::Hold),
+        ]
+        .iter()
+        .filter(|(control, _)| match self.held_keys[control.index()] {
+            Some(held_key) => held_key.is_repeating || !held_key.is_before_hard_drop,
+            None => false,
+        })
+        .map(|(_, action)| *action)
+        .collect()
+    }
+
+    /// Advances the held keys by a frame and returns the actions to apply for it. New presses
+    /// act on the frame they're handled in.
+    pub fn update(&mut self, delta_time: Duration) -> Vec<Action> {
+        let mut actions = vec![];
+
+        for held_key in self.held_keys.iter_mut().flatten() {
+            held_key.held_time += delta_time;
+            held_key.idle_time += delta_time;
+        }
+        if let Some(shift) = &mut self.shift {
+            shift.time += delta_time;
+            if let Some(charged_time) = &mut shift.charged_time {
+                *charged_time += delta_time;
+            }
+        }
+
+        // Only fresh presses act. Repeats just keep the key held so holding rotate, hold or
+        // hard drop doesn't fire them again.
+        for control in std::mem::take(&mut self.pending_presses) {
+            if !self.record_press(control) {
+                continue;
+            }
+
+            match control {
+                Control::MoveLeft | Control::MoveRight => {
+                    actions.push(shift_action(control));
+                    self.shift = Some(Shift {
+                        direction: control,
+                        time: Duration::from_millis(0),
+                        charged_time: None,
+                        repeats: 0,
+                        is_at_wall: false,
+                    });
+                }
+                Control::RotateLeft => actions.push(Action::RotateLeft),
+                Control::RotateRight => actions.push(Action::RotateRight),
+                Control::Rotate180 => actions.push(Action::Rotate180),
+                Control::HardDrop => {
+                    actions.push(Action::HardDrop);
+                    for held_key in self.held_keys.iter_mut().flatten() {
+                        held_key.is_before_hard_drop = true;
+                    }
+                }
+                Control::Hold => actions.push(Action::Hold),
+                _ => (),
+            }
+
+            // Turning or swapping the block can take it away from the wall
+            if matches!(
+                control,
+                Control::RotateLeft | Control::RotateRight | Control::Rotate180 | Control::Hold
+            ) {
+                self.block_changed();
+            }
+        }
+
+        self.release_idle_keys();
+
+        // Letting go of one direction while the other is still held shifts the other way
+        if let Some(shift) = self.shift {
+            if !self.is_held(shift.direction) {
+                let other_direction = match shift.direction {
+                    Control::MoveLeft => Control::MoveRight,
+                    _ => Control::MoveLeft,
+                };
+                self.shift = if self.is_held(other_direction) {
+                    Some(Shift {
+                        direction: other_direction,
+                        time: Duration::from_millis(0),
+                        charged_time: None,
+                        repeats: 0,
+                        is_at_wall: false,
+                    })
+                } else {
+                    None
+                };
+            }
+        }
+
+        // DAS counts from the press. The direction is only still shifting if its key is still held,
+        // which the terminal's repeats keep it.
+        if let Some(shift) = &mut self.shift {
+            if shift.charged_time.is_none() && shift.time >= self.handling.das {
+                shift.charged_time = Some(Duration::from_millis(0));
+            }
+
+            if let Some(charged_time) = shift.charged_time {
+                if self.handling.arr == Duration::from_millis(0) {
+                    if !shift.is_at_wall {
+                        actions.push(shift_to_wall_action(shift.direction));
+                        shift.is_at_wall = true;
+                    }
+                } else {
+                    let repeats =
+                        1 + (charged_time.as_micros() / self.handling.arr.as_micros()) as u32;
+                    for _ in shift.repeats..repeats {
+                        actions.push(shift_action(shift.direction));
+                    }
+                    shift.repeats = repeats;
+                }
+            }
+        }
+
+        let is_soft_drop_held = self.is_held(Control::SoftDrop);
+        if is_soft_drop_held != self.is_soft_dropping {
+            self.is_soft_dropping = is_soft_drop_held;
+            actions.push(if is_soft_drop_held {
+                Action::SoftDropStart
+            } else {
+                Action::SoftDropStop
+            });
+        }
+
+        actions
+    }
+
+    // Marks a control's key as held. Returns true for a fresh press and false for a repeat.
+    fn record_press(&mut self, control: Control) -> bool {
+        let key_repeat_delay = self.handling.key_repeat_delay;
+        let held_key = &mut self.held_keys[control.index()];
+        match held_key {
+            // A second press well before the terminal would start repeating is the player
+            // tapping the key again rather than holding it
+            Some(key) if key.is_repeating || key.held_time >= key_repeat_delay / 2 => {
+                key.is_repeating = true;
+                key.idle_time = Duration::from_millis(0);
+                false
+            }
+            _ => {
+                *held_key = Some(HeldKey {
+                    held_time: Duration::from_millis(0),
+                    idle_time: Duration::from_millis(0),
+                    is_repeating: false,
+                    is_before_hard_drop: false,
+                });
+                true
+            }
+        }
+    }
+
+    // Releases every key the terminal has stopped repeating. Keys which haven't started
+    // repeating yet are given until the terminal's repeat delay to start.
+    fn release_idle_keys(&mut self) {
+        let handling = self.handling;
+        for held_key in self.held_keys.iter_mut() {
+            if let Some(key) = held_key {
+                let release_time = if key.is_repeating {
+                    handling.key_release_time
+                } else {
+                    handling.key_repeat_delay + handling.key_release_time
+                };
+
+                if key.idle_time > release_time {
+                    *held_key = None;
+                }
+            }
+        }
+    }
+}
+
+fn shift_action(direction: Control) -> Action {
+    match direction {
+        Control::MoveLeft => Action::MoveLeft,
+        _ => Action::MoveRight,
+    }
+}
+
+fn shift_to_wall_action(direction: Control) -> Action {
+    match direction {
+        Control::MoveLeft => Action::ShiftLeftToWall,
+        _ => Action::ShiftRightToWall,
+    }
+}
@@ -0,0 +1,136 @@
+use crate::keybindings::*;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Something the player did, as the screen reading it sees it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    /// a key bound to a control on the current screen
+    Control(Control),
+    /// a key which isn't bound to anything on the current screen. Screens which read text, or
+    /// have their own keys like the replay's speed keys, read these.
+    Key(Key),
+    /// the screen changed size, so anything laid out for the old size needs laying out again
+    Resize,
+}
+
+impl InputEvent {
+    /// The event for a key press on a screen with the given context
+    pub fn from_key(key: Key, bindings: &KeyBindings, context: ControlContext) -> Self {
+        match bindings.control_for(key, context) {
+            Some(control) => InputEvent::Control(control),
+            None => InputEvent::Key(key),
+        }
+    }
+}
+
+/// Where the screens read the player's input from. The terminal front end reads key presses and
+/// tests play back a script.
+pub trait InputSource {
+    /// Waits up to `timeout` for the next input, reading keys as the controls they're bound to in
+    /// the given context. Returns None once the timeout has passed without any input.
+    fn next_event(
+        &mut self,
+        bindings: &KeyBindings,
+        context: ControlContext,
+        timeout: Duration,
+    ) -> Option<InputEvent>;
+}
+
+/// Plays back a list of events, each tagged with when it happens. Time only passes while waiting
+/// for input so a script plays back the same way however fast it's read.
+pub struct ScriptedInputSource {
+    events: VecDeque<(Duration, InputEvent)>,
+    time: Duration,
+}
+
+impl ScriptedInputSource {
+    pub fn new(mut events: Vec<(Duration, InputEvent)>) -> Self {
+        events.sort_by_key(|(time, _)| *time);
+        ScriptedInputSource {
+            events: events.into(),
+            time: Duration::from_millis(0),
+        }
+    }
+
+    /// Plays every event straight away, one per read
+    pub fn immediate(events: &[InputEvent]) -> Self {
+        Self::new(
+            events
+                .iter()
+                .map(|event| (Duration::from_millis(0), *event))
+                .collect(),
+        )
+    }
+
+    /// How much time has passed waiting for input
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl InputSource for ScriptedInputSource {
+    fn next_event(
+        &mut self,
+        _bindings: &KeyBindings,
+        _context: ControlContext,
+        timeout: Duration,
+    ) -> Option<InputEvent> {
+        match self.events.front() {
+            Some((event_time, _)) if *event_time <= self.time + timeout => {
+                self.time = self.time.max(*event_time);
+                self.events.pop_front().map(|(_, event)| event)
+            }
+            _ => {
+                self.time += timeout;
+                None
+            }
+        }
+    }
+}
+
+/// Passes another source's input through, keeping every event with the time it arrived so it
+/// can be played back later with a [`ScriptedInputSource`]
+pub struct RecordingInputSource<S: InputSource> {
+    source: S,
+    start_time: Instant,
+    events: Vec<(Duration, InputEvent)>,
+}
+
+impl<S: InputSource> RecordingInputSource<S> {
+    pub fn new(source: S) -> Self {
+        RecordingInputSource {
+            source,
+            start_time: Instant::now(),
+            events: vec![],
+        }
+    }
+
+    /// Every event read so far with how long after the recording started it arrived
+    pub fn events(&self) -> &[(Duration, InputEvent)] {
+        &self.events
+    }
+
+    pub fn into_events(self) -> Vec<(Duration, InputEvent)> {
+        self.events
+    }
+}
+
+impl<S: InputSource> InputSource for RecordingInputSource<S> {
+    fn next_event(
+        &mut self,
+        bindings: &KeyBindings,
+        context: ControlContext,
+        timeout: Duration,
+    ) -> Option<InputEvent> {
+        let event = self.source.next_event(bindings, context, timeout);
+        if let Some(event) = event {
+            self.events.push((self.start_time.elapsed(), event));
+        }
+        event
+    }
+}
@@ -0,0 +1,434 @@
+use crate::config::*;
+use std::fmt;
+
+/// A key on the keyboard, independent of the terminal library reading it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Left,
+    Right,
+    Up,
+    Down,
+    Enter,
+    Esc,
+    Backspace,
+}
+
+impl Key {
+    /// Parses a key as written in the config file: a single character or one of `space`, `left`,
+    /// `right`, `up`, `down`, `enter`, `esc` and `backspace`
+    pub fn from_name(name: &str) -> Option<Key> {
+        match name {
+            "space" => Some(Key::Char(' ')),
+            "left" => Some(Key::Left),
+            "right" => Some(Key::Right),
+            "up" => Some(Key::Up),
+            "down" => Some(Key::Down),
+            "enter" => Some(Key::Enter),
+            "esc" => Some(Key::Esc),
+            "backspace" => Some(Key::Backspace),
+            _ => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) if !ch.is_whitespace() && !ch.is_control() => {
+                        Some(Key::Char(ch))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Key::Char(' ') => String::from("space"),
+            Key::Char(ch) => ch.to_string(),
+            Key::Left => String::from("left"),
+            Key::Right => String::from("right"),
+            Key::Up => String::from("up"),
+            Key::Down => String::from("down"),
+            Key::Enter => String::from("enter"),
+            Key::Esc => String::from("esc"),
+            Key::Backspace => String::from("backspace"),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The screens a control is read on. Each key may only be bound once per context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlContext {
+    Game,
+    Menu,
+    /// watching a replay
+    Replay,
+    /// screens which read typed text, like the leaderboard initials. No controls are read here so
+    /// every key comes through as it is.
+    Text,
+}
+
+/// Something the player can bind keys to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    MoveLeft,
+    MoveRight,
+    RotateLeft,
+    RotateRight,
+    Rotate180,
+    SoftDrop,
+    HardDrop,
+    Hold,
+    Pause,
+    Quit,
+    SlowDown,
+    ResetSpeed,
+    SpeedUp,
+    ToggleFps,
+    MenuUp,
+    MenuDown,
+    MenuSelect,
+    ReplaySpeed1x,
+    ReplaySpeed2x,
+    ReplaySpeed4x,
+    ReplayStep,
+}
+
+impl Control {
+    pub const ALL: [Control; 21] = [
+        Control::MoveLeft,
+        Control::MoveRight,
+        Control::RotateLeft,
+        Control::RotateRight,
+        Control::Rotate180,
+        Control::SoftDrop,
+        Control::HardDrop,
+        Control::Hold,
+        Control::Pause,
+        Control::Quit,
+        Control::SlowDown,
+        Control::ResetSpeed,
+        Control::SpeedUp,
+        Control::ToggleFps,
+        Control::MenuUp,
+        Control::MenuDown,
+        Control::MenuSelect,
+        Control::ReplaySpeed1x,
+        Control::ReplaySpeed2x,
+        Control::ReplaySpeed4x,
+        Control::ReplayStep,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Control> {
+        Control::ALL
+            .iter()
+            .copied()
+            .find(|control| control.name() == name)
+    }
+
+    /// The name used for the control in the config file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Control::MoveLeft => "move_left",
+            Control::MoveRight => "move_right",
+            Control::RotateLeft => "rotate_left",
+            Control::RotateRight => "rotate_right",
+            Control::Rotate180 => "rotate_180",
+            Control::SoftDrop => "soft_drop",
+            Control::HardDrop => "hard_drop",
+            Control::Hold => "hold",
+            Control::Pause => "pause",
+            Control::Quit => "quit",
+            Control::SlowDown => "slow_down",
+            Control::ResetSpeed => "reset_speed",
+            Control::SpeedUp => "speed_up",
+            Control::ToggleFps => "toggle_fps",
+            Control::MenuUp => "menu_up",
+            Control::MenuDown => "menu_down",
+            Control::MenuSelect => "menu_select",
+            Control::ReplaySpeed1x => "replay_speed_1x",
+            Control::ReplaySpeed2x => "replay_speed_2x",
+            Control::ReplaySpeed4x => "replay_speed_4x",
+            Control::ReplayStep => "replay_step",
+        }
+    }
+
+    /// The name shown to the player on the settings screen
+    pub fn label(&self) -> &'static str {
+        match self {
+            Control::MoveLeft => "Move left",
+            Control::MoveRight => "Move right",
+            Control::RotateLeft => "Rotate left",
+            Control::RotateRight => "Rotate right",
+            Control::Rotate180 => "Rotate 180",
+            Control::SoftDrop => "Soft drop",
+            Control::HardDrop => "Hard drop",
+            Control::Hold => "Hold",
+            Control::Pause => "Pause",
+            Control::Quit => "Quit game",
+            Control::SlowDown => "Slow down (debug)",
+            Control::ResetSpeed => "Reset speed (debug)",
+            Control::SpeedUp => "Speed up (debug)",
+            Control::ToggleFps => "Show FPS",
+            Control::MenuUp => "Menu up",
+            Control::MenuDown => "Menu down",
+            Control::MenuSelect => "Menu select",
+            Control::ReplaySpeed1x => "Replay at 1x",
+            Control::ReplaySpeed2x => "Replay at 2x",
+            Control::ReplaySpeed4x => "Replay at 4x",
+            Control::ReplayStep => "Replay step",
+        }
+    }
+
+    /// The screens the control is read on. Pausing and quitting work the same in replays as they
+    /// do in the game.
+    pub fn contexts(&self) -> &'static [ControlContext] {
+        match self {
+            Control::Pause | Control::Quit => &[ControlContext::Game, ControlContext::Replay],
+            Control::MenuUp | Control::MenuDown | Control::MenuSelect => &[ControlContext::Menu],
+            Control::ReplaySpeed1x
+            | Control::ReplaySpeed2x
+            | Control::ReplaySpeed4x
+            | Control::ReplayStep => &[ControlContext::Replay],
+            _ => &[ControlContext::Game],
+        }
+    }
+
+    pub(crate) fn index(&self) -> usize {
+        Control::ALL.iter().position(|c| c == self).unwrap()
+    }
+
+    fn default_keys(&self) -> Vec<Key> {
+        match self {
+            Control::MoveLeft => vec![Key::Char('a'), Key::Left],
+            Control::MoveRight => vec![Key::Char('d'), Key::Right],
+            Control::RotateLeft => vec![Key::Char('q'), Key::Char('z')],
+            Control::RotateRight => vec![Key::Char('e'), Key::Char('x'), Key::Up],
+            Control::Rotate180 => vec![Key::Char('r')],
+            Control::SoftDrop => vec![Key::Char('s'), Key::Down],
+            Control::HardDrop => vec![Key::Char(' ')],
+            Control::Hold => vec![Key::Char('w'), Key::Char('c')],
+            Control::Pause => vec![Key::Char('p')],
+            Control::Quit => vec![Key::Esc],
+            Control::SlowDown => vec![Key::Char('[')],
+            Control::ResetSpeed => vec![Key::Char('\\')],
+            Control::SpeedUp => vec![Key::Char(']')],
+            Control::ToggleFps => vec![Key::Char('f')],
+            Control::MenuUp => vec![Key::Char('w'), Key::Up],
+            Control::MenuDown => vec![Key::Char('s'), Key::Down],
+            Control::MenuSelect => vec![Key::Enter],
+            Control::ReplaySpeed1x => vec![Key::Char('1')],
+            Control::ReplaySpeed2x => vec![Key::Char('2')],
+            Control::ReplaySpeed4x => vec![Key::Char('4')],
+            Control::ReplayStep => vec![Key::Char('.')],
+        }
+    }
+}
+
+/// The keys bound to every control. Config files list one control per line under a `[keys]`
+/// table and only need to list the controls they change:
+///
+/// ```text
+/// [keys]
+/// # rotate with the arrow keys like the original controls
+/// rotate_left = "left"
+/// rotate_right = ["right", "up"]
+/// move_left = "a"
+/// move_right = "d"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBindings {
+    // indexed the same as Control::ALL
+    keys: Vec<Vec<Key>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            keys: Control::ALL
+                .iter()
+                .map(|control| control.default_keys())
+                .collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Reads bindings from a config file, starting from the defaults. The bindings are validated
+    /// so every control has a key and no key is bound twice on the same screen.
+    pub fn parse<S: AsRef<str>>(config: S) -> Result<Self, String> {
+        let mut bindings = KeyBindings::default();
+        parse_table(config.as_ref(), "keys", |name, value| {
+            let control =
+                Control::from_name(name).ok_or_else(|| format!("unknown control '{}'", name))?;
+            bindings.keys[control.index()] = parse_key_list(value)?;
+            Ok(())
+        })?;
+
+        bindings.validate()?;
+        Ok(bindings)
+    }
+
+    pub fn to_config_string(&self) -> String {
+        let mut config = String::from("[keys]\n");
+        for control in Control::ALL.iter() {
+            let keys = self
+                .keys(*control)
+                .iter()
+                .map(|key| format!("\"{}\"", escape_key_name(&key.name())))
+                .collect::<Vec<String>>()
+                .join(", ");
+            config.push_str(&format!("{} = [{}]\n", control.name(), keys));
+        }
+        config
+    }
+
+    pub fn keys(&self, control: Control) -> &[Key] {
+        &self.keys[control.index()]
+    }
+
+    /// The control a key is bound to on the given screen, if any
+    pub fn control_for(&self, key: Key, context: ControlContext) -> Option<Control> {
+        Control::ALL
+            .iter()
+            .copied()
+            .filter(|control| control.contexts().contains(&context))
+            .find(|control| self.keys(*control).contains(&key))
+    }
+
+    /// Replaces the keys bound to a control. The old keys are kept if the new ones would leave
+    /// the bindings invalid.
+    pub fn rebind(&mut self, control: Control, keys: Vec<Key>) -> Result<(), String> {
+        let old_keys = std::mem::replace(&mut self.keys[control.index()], keys);
+        if let Err(e) = self.validate() {
+            self.keys[control.index()] = old_keys;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Adds a key to a control's keys, or takes it away if the control already has it. The other
+    /// keys are left alone, and nothing changes if it would leave the bindings invalid.
+    pub fn toggle_key(&mut self, control: Control, key: Key) -> Result<(), String> {
+        let mut keys = self.keys(control).to_vec();
+        match keys.iter().position(|bound_key| *bound_key == key) {
+            Some(i) => {
+                keys.remove(i);
+            }
+            None => keys.push(key),
+        }
+        self.rebind(control, keys)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (i, control) in Control::ALL.iter().enumerate() {
+            let keys = self.keys(*control);
+            if keys.is_empty() {
+                return Err(format!("no keys are bound to {}", control.name()));
+            }
+
+            for (j, key) in keys.iter().enumerate() {
+                if keys[..j].contains(key) {
+                    return Err(format!("'{}' is bound to {} twice", key, control.name()));
+                }
+            }
+
+            for other_control in &Control::ALL[..i] {
+                let shares_context = other_control
+                    .contexts()
+                    .iter()
+                    .any(|context| control.contexts().contains(context));
+                if !shares_context {
+                    continue;
+                }
+
+                if let Some(key) = keys
+                    .iter()
+                    .find(|key| self.keys(*other_control).contains(key))
+                {
+                    return Err(format!(
+                        "'{}' is bound to both {} and {}",
+                        key,
+                        other_control.name(),
+                        control.name()
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Parses either a single quoted key name or a list of them: "a" or ["a", "left"]
+fn parse_key_list(value: &str) -> Result<Vec<Key>, String> {
+    let is_list = value.starts_with('[');
+    let mut rest = if is_list {
+        value[1..].trim_start()
+    } else {
+        value
+    };
+
+    let mut names = vec![];
+    loop {
+        if is_list && rest.starts_with(']') {
+            rest = &rest[1..];
+            break;
+        }
+
+        let (name, after_name) = parse_string(rest)?;
+        names.push(name);
+        rest = after_name.trim_start();
+        if !is_list {
+            break;
+        }
+
+        // allow a trailing comma before the closing bracket
+        if rest.starts_with(',') {
+            rest = rest[1..].trim_start();
+        } else if !rest.starts_with(']') {
+            return Err(format!("expected ',' or ']' in {}", value));
+        }
+    }
+
+    if !rest.trim().is_empty() {
+        return Err(format!("unexpected '{}' after the keys", rest.trim()));
+    }
+
+    names
+        .iter()
+        .map(|name| Key::from_name(name).ok_or_else(|| format!("unknown key '{}'", name)))
+        .collect()
+}
+
+// Parses a quoted string from the start of the value and returns it with the rest of the value
+fn parse_string(value: &str) -> Result<(String, &str), String> {
+    if !value.starts_with('"') {
+        return Err(format!("expected a quoted key name but found {}", value));
+    }
+
+    let mut string = String::new();
+    let mut chars = value.char_indices().skip(1);
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some((_, '\\')) => string.push('\\'),
+                Some((_, '"')) => string.push('"'),
+                _ => return Err(format!("bad escape in {}", value)),
+            },
+            '"' => return Ok((string, &value[i + 1..])),
+            _ => string.push(ch),
+        }
+    }
+
+    Err(format!("missing closing quote in {}", value))
+}
+
+fn escape_key_name(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -1,25 +1,56 @@
+/// A named result on a leaderboard
 #[derive(Debug, PartialEq, Eq, Savefile)]
 pub struct LeaderboardEntry {
     pub name: String,
+    /// the score for scored modes or the final time in milliseconds for timed modes
     pub score: usize,
+    /// the seed the game was played with so the run can be reproduced. Entries saved before seeds
+    /// were recorded load with a seed of 0.
+    #[savefile_versions = "1.."]
+    pub seed: u64,
 }
 
-#[derive(Debug, PartialEq, Eq, Savefile)]
+/// Which end of the leaderboard the best results go to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeaderboardOrder {
+    HighestFirst,
+    LowestFirst,
+}
+
+impl LeaderboardOrder {
+    fn is_better(&self, score: usize, other_score: usize) -> bool {
+        match self {
+            LeaderboardOrder::HighestFirst => score > other_score,
+            LeaderboardOrder::LowestFirst => score < other_score,
+        }
+    }
+}
+
+/// The best results for a game mode, best first, saved to and loaded from disk
+#[derive(Debug, Default, PartialEq, Eq, Savefile)]
 pub struct Leaderboard {
     entries: Vec<LeaderboardEntry>,
 }
 
 impl LeaderboardEntry {
+    /// An entry with no seed recorded, which is stored as a seed of 0
     pub fn new<S: AsRef<str>>(name: S, score: usize) -> LeaderboardEntry {
         assert_eq!(name.as_ref().len(), Leaderboard::entry_name_len());
         LeaderboardEntry {
             name: String::from(name.as_ref()),
             score,
+            seed: 0,
         }
     }
+
+    pub fn with_seed(mut self, seed: u64) -> LeaderboardEntry {
+        self.seed = seed;
+        self
+    }
 }
 
 impl Leaderboard {
+    /// Every entry's name is exactly this many characters
     pub const fn entry_name_len() -> usize {
         3
     }
@@ -28,6 +59,9 @@ impl Leaderboard {
         10
     }
 
+    // bumped whenever the saved layout changes so older leaderboard files still load
+    const SAVE_VERSION: u32 = 1;
+
     pub fn new() -> Self {
         Leaderboard { entries: vec![] }
     }
@@ -37,25 +71,28 @@ impl Leaderboard {
         Leaderboard { entries }
     }
 
+    /// Reads a leaderboard from a file written by [`Leaderboard::save`]
     pub fn load<S: AsRef<str>>(file_name: S) -> Result<Self, String> {
-        let load_operation = savefile::load_file(file_name.as_ref(), 0);
+        let load_operation = savefile::load_file(file_name.as_ref(), Self::SAVE_VERSION);
         let loaded_leaderboard = load_operation.map_err(|e| format!("{}", e))?;
         Ok(loaded_leaderboard)
     }
 
+    /// Writes the leaderboard to a file, panicking if it can't be written
     pub fn save<S: AsRef<str>>(&self, file_name: S) {
         // TODO: how would I handle save errors? crash the game? print some diagnostic log?
-        savefile::save_file(file_name.as_ref(), 0, self).unwrap()
+        savefile::save_file(file_name.as_ref(), Self::SAVE_VERSION, self).unwrap()
     }
 
     #[cfg(test)]
     pub fn serialize(&self) -> Vec<u8> {
-        savefile::save_to_mem(0, self).unwrap()
+        savefile::save_to_mem(Self::SAVE_VERSION, self).unwrap()
     }
 
-    pub fn get_place_on_leaderboard(&self, score: usize) -> Option<usize> {
+    /// The index a new result would be inserted at or `None` if it doesn't make the leaderboard
+    pub fn get_place_on_leaderboard(&self, score: usize, order: LeaderboardOrder) -> Option<usize> {
         for (i, entry) in self.entries.iter().enumerate() {
-            if score > entry.score {
+            if order.is_better(score, entry.score) {
                 return Some(i);
             }
         }
@@ -68,18 +105,24 @@ impl Leaderboard {
         None
     }
 
-    pub fn add_score<S: AsRef<str>>(&mut self, name: S, score: usize) {
-        let place = self.get_place_on_leaderboard(score);
+    /// Inserts a new result with no seed. Panics if the result doesn't make the leaderboard.
+    pub fn add_score<S: AsRef<str>>(&mut self, name: S, score: usize, order: LeaderboardOrder) {
+        self.add_entry(LeaderboardEntry::new(name, score), order);
+    }
+
+    /// Inserts a new entry. Panics if the entry doesn't make the leaderboard.
+    pub fn add_entry(&mut self, entry: LeaderboardEntry, order: LeaderboardOrder) {
+        let place = self.get_place_on_leaderboard(entry.score, order);
         assert!(place.is_some());
 
         let place = place.unwrap();
-        self.entries
-            .insert(place, LeaderboardEntry::new(name, score));
+        self.entries.insert(place, entry);
         if self.entries.len() > Leaderboard::max_entries() {
             self.entries.pop();
         }
     }
 
+    /// The entry at `index` or `None` if there are fewer entries than that
     pub fn entry<'a>(&'a self, index: usize) -> Option<&'a LeaderboardEntry> {
         if index < self.entries.len() {
             Some(&self.entries[index])
@@ -0,0 +1,61 @@
+//! The tetrust game engine, free of any terminal code so it can be embedded in other tools.
+//!
+//! [`GameState`] holds a game in progress. Front ends feed it the player's inputs, call
+//! [`GameState::update`] every frame with however much time has passed and draw whatever state
+//! they need from its accessors.
+//!
+//! ```
+//! use std::time::Duration;
+//! use tetrust::*;
+//!
+//! let mut game_state = GameStateBuilder::new(10, 20).build();
+//! game_state.update(Duration::from_millis(250));
+//! game_state.hard_drop();
+//! println!("score: {}", game_state.score());
+//! ```
+
+#[macro_use]
+extern crate savefile_derive;
+extern crate savefile;
+extern crate snm_rand_utils;
+
+pub mod action;
+pub mod block;
+pub mod config;
+pub mod game;
+pub mod handling;
+pub mod input;
+pub mod keybindings;
+pub mod leaderboard;
+pub mod pacing;
+pub mod piece_generator;
+pub mod render;
+pub mod replay;
+pub mod rotation;
+pub mod screens;
+pub mod seeded_rng;
+pub mod simulator;
+mod tests;
+pub mod util;
+
+pub use crate::action::Action;
+pub use crate::block::{Block, BlockType, Rotation, BLOCKTYPES};
+pub use crate::config::Config;
+pub use crate::game::{
+    GameEvent, GameMode, GameOverReason, GameState, GameStateBuilder, LineClear, LockDelay, Rules,
+    SpawnDelay, TSpin,
+};
+pub use crate::handling::{Handling, InputHandler};
+pub use crate::input::{InputEvent, InputSource, RecordingInputSource, ScriptedInputSource};
+pub use crate::keybindings::{Control, ControlContext, Key, KeyBindings};
+pub use crate::leaderboard::{Leaderboard, LeaderboardEntry, LeaderboardOrder};
+pub use crate::pacing::{Display, FixedTimestep, FramePacer, FrameStats};
+pub use crate::piece_generator::{PieceGenerator, PieceGeneratorType};
+pub use crate::render::{Color, GridRenderer, Renderer, Style};
+pub use crate::replay::{Replay, ReplayFrame};
+pub use crate::rotation::{
+    ArikaRotationSystem, ClassicRotationSystem, RotationSystem, RotationSystemType,
+    SuperRotationSystem, ARS, CLASSIC, SRS,
+};
+pub use crate::seeded_rng::SeededRangeRng;
+pub use crate::util::Vec2;
@@ -1,25 +1,27 @@
-#[macro_use]
-extern crate savefile_derive;
 extern crate pancurses;
-extern crate savefile;
-extern crate snm_rand_utils;
-
-mod block;
-mod game;
-mod leaderboard;
-mod tests;
-mod util;
-
-use crate::block::*;
-use crate::game::*;
-use crate::leaderboard::*;
-use crate::util::*;
-use snm_rand_utils::range_rng::*;
-use std::time;
+extern crate tetrust;
 
-const TITLE: &str = "TETRUST";
+use std::time;
+use tetrust::action::*;
+use tetrust::block::*;
+use tetrust::config::*;
+use tetrust::game::*;
+use tetrust::handling::*;
+use tetrust::input::*;
+use tetrust::keybindings::*;
+use tetrust::leaderboard::*;
+use tetrust::pacing::*;
+use tetrust::render::*;
+use tetrust::replay::*;
+use tetrust::screens::*;
+use tetrust::seeded_rng::*;
+use tetrust::simulator::*;
+use tetrust::util::*;
 
 const LEADERBOARD_FILE_NAME: &str = "data/leaderboard.bin";
+const SPRINT_LEADERBOARD_FILE_NAME: &str = "data/sprint_leaderboard.bin";
+const ULTRA_LEADERBOARD_FILE_NAME: &str = "data/ultra_leaderboard.bin";
+const REPLAY_FILE_NAME: &str = "data/last_game.replay";
 
 const ASCII_ESC: char = 27 as char;
 const ASCII_BACKSPACE: char = 8 as char;
@@ -85,398 +87,488 @@ impl Colors {
     }
 }
 
-fn render_cell(
-    window: &pancurses::Window,
-    cell_rel_pos: Vec2,
-    rel_pos_offset_x: i32,
-    rel_pos_offset_y: i32,
-    block_type: BlockType,
-) {
-    let sprite_char = block_type.sprite_char();
-    let color_pair = Colors::get_block_color_pair(block_type);
-    window.attron(color_pair);
-    window.mvaddch(
-        cell_rel_pos.y + rel_pos_offset_y,
-        cell_rel_pos.x + rel_pos_offset_x,
-        sprite_char,
-    );
-    window.attroff(color_pair);
-}
-
-fn render_block(
-    window: &pancurses::Window,
-    block_rel_pos: Vec2,
-    rel_pos_offset_x: i32,
-    rel_pos_offset_y: i32,
-    block: Block,
-) {
-    let sprite_char = block.sprite_char();
-    let color_pair = Colors::get_block_color_pair(block.block_type);
-    window.attron(color_pair);
-    for cell_pos in &block.cells() {
-        // Ok to blit block sprite even if position is OOB
-        window.mvaddch(
-            cell_pos.y + block_rel_pos.y + rel_pos_offset_y,
-            cell_pos.x + block_rel_pos.x + rel_pos_offset_x,
-            sprite_char,
-        );
+// Draws to a curses window
+struct TerminalRenderer<'a> {
+    window: &'a pancurses::Window,
+}
+
+impl<'a> TerminalRenderer<'a> {
+    fn new(window: &'a pancurses::Window) -> Self {
+        TerminalRenderer { window }
+    }
+
+    fn attributes(style: Style) -> pancurses::chtype {
+        let mut attributes = match style.color {
+            Color::Default => 0,
+            Color::Block(block_type) => Colors::get_block_color_pair(block_type),
+            Color::Menu(i) => Colors::get_menu_color_pair(i % Colors::MENU_COLOR_PALETTE.len()),
+        };
+        if style.blink {
+            attributes |= pancurses::A_BLINK;
+        }
+        if style.dim {
+            attributes |= pancurses::A_DIM;
+        }
+        attributes
     }
-    window.attroff(color_pair);
 }
 
-fn draw_frame(window: &pancurses::Window, frame_rect: &Rect) {
-    let left = frame_rect.left;
-    let top = frame_rect.top;
-    let right = frame_rect.right();
-    let bottom = frame_rect.bottom();
+impl<'a> Renderer for TerminalRenderer<'a> {
+    fn size(&self) -> (i32, i32) {
+        self.window.get_max_yx()
+    }
+
+    fn clear(&mut self) {
+        self.window.erase();
+    }
+
+    fn draw_char(&mut self, x: i32, y: i32, ch: char, style: Style) {
+        let attributes = Self::attributes(style);
+        self.window.attron(attributes);
+        self.window.mvaddch(y, x, ch);
+        self.window.attroff(attributes);
+    }
 
-    // draw corners
-    window.mvaddch(top, left, pancurses::ACS_ULCORNER());
-    window.mvaddch(top, right, pancurses::ACS_URCORNER());
-    window.mvaddch(bottom, left, pancurses::ACS_LLCORNER());
-    window.mvaddch(bottom, right, pancurses::ACS_LRCORNER());
+    // curses has proper line drawing characters for the corners and edges
+    fn draw_frame(&mut self, frame_rect: &Rect) {
+        let window = self.window;
+        let left = frame_rect.left;
+        let top = frame_rect.top;
+        let right = frame_rect.right();
+        let bottom = frame_rect.bottom();
+
+        // draw corners
+        window.mvaddch(top, left, pancurses::ACS_ULCORNER());
+        window.mvaddch(top, right, pancurses::ACS_URCORNER());
+        window.mvaddch(bottom, left, pancurses::ACS_LLCORNER());
+        window.mvaddch(bottom, right, pancurses::ACS_LRCORNER());
+
+        // draw horizontal borders
+        for col in left + 1..right {
+            window.mvaddch(top, col, pancurses::ACS_HLINE());
+            window.mvaddch(bottom, col, pancurses::ACS_HLINE());
+        }
 
-    // draw horizontal borders
-    for col in left + 1..right {
-        window.mvaddch(top, col, pancurses::ACS_HLINE());
-        window.mvaddch(bottom, col, pancurses::ACS_HLINE());
+        // draw vertical borders
+        for row in top + 1..bottom {
+            window.mvaddch(row, left, pancurses::ACS_VLINE());
+            window.mvaddch(row, right, pancurses::ACS_VLINE());
+        }
     }
 
-    // draw vertical borders
-    for row in top + 1..bottom {
-        window.mvaddch(row, left, pancurses::ACS_VLINE());
-        window.mvaddch(row, right, pancurses::ACS_VLINE());
+    fn present(&mut self) {
+        self.window.refresh();
     }
 }
 
-fn draw_text_centered<S>(window: &pancurses::Window, text: S, x_center: i32, y_center: i32)
-where
-    S: AsRef<str>,
-{
-    window.mvaddstr(y_center, x_center - (text.as_ref().len() / 2) as i32, text);
+// Maps a terminal input to the key it was pressed with, if it's one the game can bind
+fn input_key(input: pancurses::Input) -> Option<Key> {
+    match input {
+        pancurses::Input::Character(ASCII_ENTER) | pancurses::Input::KeyEnter => Some(Key::Enter),
+        pancurses::Input::Character(ASCII_ESC) => Some(Key::Esc),
+        pancurses::Input::Character(ASCII_BACKSPACE)
+        | pancurses::Input::Character(ASCII_DEL)
+        | pancurses::Input::KeyBackspace
+        | pancurses::Input::KeyDC => Some(Key::Backspace),
+        pancurses::Input::Character(ch) => Some(Key::Char(ch)),
+        pancurses::Input::KeyLeft => Some(Key::Left),
+        pancurses::Input::KeyRight => Some(Key::Right),
+        pancurses::Input::KeyUp => Some(Key::Up),
+        pancurses::Input::KeyDown => Some(Key::Down),
+        _ => None,
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Screen {
-    StartMenu,
-    Game,
-    LeaderboardUpdate(usize),
-    Leaderboard,
+// Reads key presses from a curses window
+struct TerminalInput<'a> {
+    window: &'a pancurses::Window,
 }
 
-fn run_start_menu(window: &pancurses::Window) -> Option<Screen> {
-    const TITLE_LINES: [&str; 7] = [
-        r#" _____________"#,
-        r#"/\____________\ ___  _____  ___  .   .   ___   _____"#,
-        r#"\/___/\   \___/ \___    \   \ _)  \   \  \ ___    \"#,
-        r#"     \ \   \     \___    \   \  \  \___\   ___\    \"#,
-        r#"      \ \   \"#,
-        r#"       \ \___\"#,
-        r#"        \/___/"#,
-    ];
+impl<'a> TerminalInput<'a> {
+    fn new(window: &'a pancurses::Window) -> Self {
+        TerminalInput { window }
+    }
+}
 
-    let (window_height, window_width) = window.get_max_yx();
+impl<'a> InputSource for TerminalInput<'a> {
+    fn next_event(
+        &mut self,
+        bindings: &KeyBindings,
+        context: ControlContext,
+        timeout: time::Duration,
+    ) -> Option<InputEvent> {
+        let deadline = time::Instant::now() + timeout;
+        loop {
+            let wait_time = deadline.saturating_duration_since(time::Instant::now());
+
+            // round up so the wait doesn't end just before the deadline
+            self.window
+                .timeout(wait_time.as_micros().div_ceil(1000) as i32);
+            match self.window.getch() {
+                Some(pancurses::Input::KeyResize) => {
+                    // let curses pick up the new size before anything is drawn at it
+                    pancurses::resize_term(0, 0);
+                    return Some(InputEvent::Resize);
+                }
+                // keys the game can't bind are skipped
+                Some(input) => {
+                    if let Some(key) = input_key(input) {
+                        return Some(InputEvent::from_key(key, bindings, context));
+                    }
+                }
+                None if wait_time == time::Duration::from_millis(0) => return None,
+                None => (),
+            }
+        }
+    }
+}
 
-    let title_rect = {
-        let title_width = TITLE_LINES.iter().map(|line| line.len()).max().unwrap() as i32;
-        const TITLE_HEIGHT: i32 = TITLE_LINES.len() as i32;
+// Blocks until a key is pressed or the next frame is due. Returns None once the frame is due.
+fn wait_for_frame_input(
+    input: &mut dyn InputSource,
+    bindings: &KeyBindings,
+    context: ControlContext,
+    frame_pacer: &FramePacer,
+) -> Option<InputEvent> {
+    let wait_time = frame_pacer.time_until_next_frame(time::Instant::now());
+    input.next_event(bindings, context, wait_time)
+}
 
-        Rect {
-            // center the title horizontally
-            left: (window_width - title_width) / 2,
-            // place the title just above the horizontal divide
-            top: (window_height / 2) - (TITLE_HEIGHT + 1),
-            width: title_width,
-            height: TITLE_HEIGHT,
-        }
-    };
+// Menus only need to redraw when a key is pressed so they block on input, waking up now and then
+// in case anything else changed
+fn wait_for_menu_input(
+    input: &mut dyn InputSource,
+    bindings: &KeyBindings,
+    context: ControlContext,
+) -> Option<InputEvent> {
+    const MENU_INPUT_TIMEOUT: time::Duration = time::Duration::from_millis(100);
+    input.next_event(bindings, context, MENU_INPUT_TIMEOUT)
+}
 
-    let mut menu_cursor: usize = 0;
-    const MENU_OPTIONS: [&str; 3] = ["Start Game", "Leaderboard", "Quit"];
-    const MENU_OPTION_RESULTS: [Option<Screen>; MENU_OPTIONS.len()] =
-        [Some(Screen::Game), Some(Screen::Leaderboard), None];
+// Moves a menu cursor up or down, wrapping around at either end
+fn move_menu_cursor(menu_cursor: usize, option_count: usize, control: Control) -> usize {
+    match control {
+        Control::MenuUp if menu_cursor == 0 => option_count - 1,
+        Control::MenuUp => menu_cursor - 1,
+        Control::MenuDown if menu_cursor == option_count - 1 => 0,
+        Control::MenuDown => menu_cursor + 1,
+        _ => menu_cursor,
+    }
+}
 
-    let menu_rect = {
-        let menu_width = MENU_OPTIONS
-            .iter()
-            .map(|option_text| option_text.len())
-            .max()
-            .unwrap() as i32;
-        const MENU_HEIGHT: i32 = MENU_OPTIONS.len() as i32;
-
-        Rect {
-            // center the menu options horizontally
-            left: (window_width - menu_width) / 2,
-            // place the menu options just below the horizontal divide
-            top: (window_height / 2) + 1,
-            // Add 2 characters to the menu width to account for the cursor
-            width: menu_width + 2,
-            height: MENU_HEIGHT,
-        }
-    };
+// Every mode keeps its own leaderboard. Marathon and ultra rank the highest scores and sprint
+// ranks the fastest times (in milliseconds).
+fn leaderboard_file_name(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Marathon => LEADERBOARD_FILE_NAME,
+        GameMode::Sprint { .. } => SPRINT_LEADERBOARD_FILE_NAME,
+        GameMode::Ultra { .. } => ULTRA_LEADERBOARD_FILE_NAME,
+    }
+}
 
-    loop {
-        // clear the screen
-        window.erase();
-
-        // Render the title card
-        for (i, title_line) in TITLE_LINES.iter().enumerate() {
-            let row_offset = (i as i32) + title_rect.top;
-            let color_pair = Colors::get_menu_color_pair(i % 4);
-            window.attron(color_pair);
-            window.mvaddstr(row_offset, title_rect.left, title_line);
-            window.attroff(color_pair);
-        }
-
-        // Render the menu options
-        for (i, menu_line) in MENU_OPTIONS.iter().enumerate() {
-            let row_offset = (i as i32) + menu_rect.top;
-            if i == menu_cursor {
-                window.mvaddstr(row_offset, menu_rect.left, "> ");
-            }
-            window.mvaddstr(row_offset, menu_rect.left + 2, menu_line);
-        }
+fn leaderboard_order(mode: GameMode) -> LeaderboardOrder {
+    match mode {
+        GameMode::Marathon | GameMode::Ultra { .. } => LeaderboardOrder::HighestFirst,
+        GameMode::Sprint { .. } => LeaderboardOrder::LowestFirst,
+    }
+}
 
-        // Input handling
-        // TODO: I think this input system might need some refactoring to share with the start menu
-        if let Some(pancurses::Input::Character(ch)) = window.getch() {
-            match ch {
-                // check for movement inputs
-                'w' => {
-                    menu_cursor = if menu_cursor == 0 {
-                        MENU_OPTIONS.len() - 1
-                    } else {
-                        menu_cursor - 1
-                    }
-                }
-                's' => {
-                    menu_cursor = if menu_cursor == MENU_OPTIONS.len() - 1 {
-                        0
-                    } else {
-                        menu_cursor + 1
-                    }
-                }
-                ASCII_ENTER => return MENU_OPTION_RESULTS[menu_cursor],
-                _ => (),
-            }
-        };
+// Where the seed for each new game comes from
+#[derive(Debug, Clone, Copy)]
+enum SeedChoice {
+    // a fresh seed every game
+    Random,
+    // the same seed every game, set with --seed
+    Fixed(u64),
+    // one seed for everyone playing on the same day, set with --daily
+    Daily,
+}
 
-        // blit the next frame
-        window.refresh();
+impl SeedChoice {
+    fn game_seed(&self) -> u64 {
+        match self {
+            SeedChoice::Random => time::SystemTime::now()
+                .duration_since(time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos() as u64,
+            SeedChoice::Fixed(seed) => *seed,
+            SeedChoice::Daily => SeededRangeRng::daily_seed(time::SystemTime::now()),
+        }
     }
 }
 
-fn run_game(window: &pancurses::Window) -> Option<Screen> {
-    const INPUT_POLL_PERIOD: time::Duration = time::Duration::from_millis(125);
-    let mut frame_speed_modifier = 1.0f32;
+#[derive(Debug, Clone, Copy)]
+enum MenuTarget {
+    Game,
+    Leaderboard,
+}
 
-    let mut last_frame_time = time::Instant::now();
-    let mut last_input_handled = time::Instant::now();
+#[derive(Debug, Clone, Copy)]
+enum Screen {
+    StartMenu,
+    ModeSelect(MenuTarget),
+    Game(GameMode),
+    LeaderboardUpdate(GameMode, usize, u64),
+    Leaderboard(GameMode),
+    WatchReplay,
+    Settings,
+}
 
-    // standard tetris board size
-    const BOARD_WIDTH: i32 = 10;
-    const BOARD_HEIGHT: i32 = 20;
+fn run_start_menu(
+    renderer: &mut dyn Renderer,
+    input: &mut dyn InputSource,
+    bindings: &KeyBindings,
+) -> Option<Screen> {
+    run_title_menu(
+        renderer,
+        input,
+        bindings,
+        &[
+            ("Start Game", Some(Screen::ModeSelect(MenuTarget::Game))),
+            (
+                "Leaderboard",
+                Some(Screen::ModeSelect(MenuTarget::Leaderboard)),
+            ),
+            ("Watch Replay", Some(Screen::WatchReplay)),
+            ("Settings", Some(Screen::Settings)),
+            ("Quit", None),
+        ],
+    )
+}
 
-    let (window_height, window_width) = window.get_max_yx();
-    let board_rect = Rect {
-        left: (window_width / 2) - BOARD_WIDTH - 2, // arrange the board on the left side of the middle of the screen
-        top: (window_height - BOARD_HEIGHT) / 2,    // center the board within the window
-        width: BOARD_WIDTH,
-        height: 20,
+fn run_mode_select(
+    renderer: &mut dyn Renderer,
+    input: &mut dyn InputSource,
+    bindings: &KeyBindings,
+    target: MenuTarget,
+) -> Option<Screen> {
+    let mode_screen = |mode: GameMode| match target {
+        MenuTarget::Game => Some(Screen::Game(mode)),
+        MenuTarget::Leaderboard => Some(Screen::Leaderboard(mode)),
     };
 
-    let board_frame_rect = Rect {
-        left: board_rect.left - 1,
-        top: board_rect.top - 1,
-        width: board_rect.width + 2,
-        height: board_rect.height + 2,
-    };
+    run_title_menu(
+        renderer,
+        input,
+        bindings,
+        &[
+            (
+                mode_name(GameMode::Marathon),
+                mode_screen(GameMode::Marathon),
+            ),
+            (
+                mode_name(GameMode::sprint()),
+                mode_screen(GameMode::sprint()),
+            ),
+            (mode_name(GameMode::ultra()), mode_screen(GameMode::ultra())),
+            ("Back", Some(Screen::StartMenu)),
+        ],
+    )
+}
 
-    let title_rect = Rect {
-        left: board_frame_rect.right() + 2,
-        top: board_frame_rect.top,
-        width: (TITLE.len() + 4) as i32,
-        height: 3,
-    };
+// Shows the title card above a list of options and returns the result of the chosen option
+fn run_title_menu(
+    renderer: &mut dyn Renderer,
+    input: &mut dyn InputSource,
+    bindings: &KeyBindings,
+    menu_options: &[(&str, Option<Screen>)],
+) -> Option<Screen> {
+    let option_labels = menu_options
+        .iter()
+        .map(|(option_text, _)| *option_text)
+        .collect::<Vec<&str>>();
+    let mut menu_cursor: usize = 0;
 
-    let preview_frame_rect = Rect {
-        left: title_rect.left,
-        top: title_rect.bottom() + 2,
-        width: 6,
-        height: 6,
-    };
+    loop {
+        render_title_menu(renderer, &option_labels, menu_cursor);
 
-    let preview_rect = Rect {
-        left: preview_frame_rect.left + 1,
-        top: preview_frame_rect.top + 1,
-        width: preview_frame_rect.width - 2,
-        height: preview_frame_rect.height - 2,
-    };
+        // Input handling
+        match wait_for_menu_input(input, bindings, ControlContext::Menu) {
+            Some(InputEvent::Control(Control::MenuSelect)) => return menu_options[menu_cursor].1,
+            Some(InputEvent::Control(control)) => {
+                menu_cursor = move_menu_cursor(menu_cursor, menu_options.len(), control)
+            }
+            _ => (),
+        }
 
-    let score_frame_rect = Rect {
-        left: preview_frame_rect.left,
-        top: preview_frame_rect.bottom() + 2,
-        width: 14,
-        height: 4,
-    };
+        // blit the next frame
+        renderer.present();
+    }
+}
 
-    let mut game_state = GameState::new(
-        board_rect.width,
-        board_rect.height,
-        Box::new(ThreadRangeRng::new()),
-    );
-
-    struct Inputs {
-        move_left: bool,
-        move_right: bool,
-        rot_left: bool,
-        rot_right: bool,
-        drop: bool,
+// Shows a banner for each line clear the game reports
+fn take_line_clear_banner(
+    game_state: &mut GameState,
+    line_clear_banner: &mut Option<(String, time::Instant)>,
+) {
+    for game_event in game_state.take_events() {
+        match game_event {
+            GameEvent::LineClear(line_clear) => {
+                *line_clear_banner = Some((line_clear_label(&line_clear), time::Instant::now()))
+            }
+            GameEvent::PerfectClear { .. } => {
+                *line_clear_banner = Some(("PERFECT CLEAR".to_string(), time::Instant::now()))
+            }
+        }
     }
+}
 
-    let mut inputs = Inputs {
-        move_left: false,
-        move_right: false,
-        rot_left: false,
-        rot_right: false,
-        drop: false,
-    };
+// The line clear banner to show, if it's still recent enough to be up
+fn visible_line_clear_banner(line_clear_banner: &Option<(String, time::Instant)>) -> Option<&str> {
+    const LINE_CLEAR_BANNER_DURATION: time::Duration = time::Duration::from_millis(1500);
+    line_clear_banner
+        .as_ref()
+        .filter(|(_, timer)| timer.elapsed() < LINE_CLEAR_BANNER_DURATION)
+        .map(|(label, _)| label.as_str())
+}
+
+fn run_game(
+    renderer: &mut dyn Renderer,
+    input: &mut dyn InputSource,
+    config: &Config,
+    mode: GameMode,
+    seed_choice: SeedChoice,
+) -> Option<Screen> {
+    let mut frame_speed_modifier = 1.0f32;
+
+    // The game runs in fixed steps of one frame each so it updates the same way however long
+    // each frame really takes
+    let mut frame_pacer = FramePacer::new(config.display.frame_rate);
+    let mut timestep = FixedTimestep::new(frame_pacer.frame_time());
+    let mut frame_stats = FrameStats::new();
+    let mut show_fps = config.display.show_fps;
+    let mut last_frame_start = time::Instant::now();
+
+    // The seed is saved in the replay and on the leaderboard so the run can be reproduced. The
+    // replay also keeps the rotation system so the game is set up from it.
+    let seed = seed_choice.game_seed();
+    let mut replay = Replay::new(seed, mode, config.rules.rotation_system);
+    let mut game_state = replay
+        .game_builder()
+        .next_queue_len(config.display.next_queue_len)
+        .build();
+
+    let (window_height, window_width) = renderer.size();
+    let mut layout = GameLayout::new(window_height, window_width, game_state.next_queue_len());
+    let mut is_too_small = is_screen_too_small(renderer);
+    let mut input_handler = InputHandler::new(config.handling);
 
     let mut game_over_blit_timer = Option::<time::Instant>::None;
+    let mut line_clear_banner = Option::<(String, time::Instant)>::None;
     let mut game_paused = false;
 
-    loop {
-        let delta_time = last_frame_time.elapsed().mul_f32(frame_speed_modifier);
-        last_frame_time = time::Instant::now();
+    'game_loop: loop {
+        // Input handling. Sleep until the next frame is due, waking up to handle each key press
+        // as it arrives so taps act on the very next frame.
+        while let Some(event) = wait_for_frame_input(
+            input,
+            &config.key_bindings,
+            ControlContext::Game,
+            &frame_pacer,
+        ) {
+            let control = match event {
+                InputEvent::Control(control) => Some(control),
+                InputEvent::Key(_) => None,
+                InputEvent::Resize => {
+                    let (window_height, window_width) = renderer.size();
+                    layout =
+                        GameLayout::new(window_height, window_width, game_state.next_queue_len());
+                    is_too_small = is_screen_too_small(renderer);
+                    None
+                }
+            };
 
-        // Input handling
-        let next_key = window.getch();
-        // TODO: I think this input system might need some refactoring to share with the start menu
-        if let Some(input) = next_key {
-            match input {
-                // check for movement inputs
-                pancurses::Input::Character('a') => inputs.move_left = true,
-                pancurses::Input::Character('d') => inputs.move_right = true,
-                pancurses::Input::Character('s') => inputs.drop = true,
-                pancurses::Input::KeyLeft => inputs.rot_left = true,
-                pancurses::Input::KeyRight => inputs.rot_right = true,
+            match control {
+                Some(Control::Quit) => break 'game_loop, // kill game early
+                Some(Control::Pause) => game_paused = !game_paused, // toggle the pause state
+                Some(Control::ToggleFps) => show_fps = !show_fps,
 
                 // debug
-                pancurses::Input::Character(ASCII_ESC) => break, // kill game early
-                pancurses::Input::Character('p') => game_paused = !game_paused, // toggle the pause state
-                pancurses::Input::Character('z') => frame_speed_modifier /= 2.0f32, // slowdown tick rate
-                pancurses::Input::Character('x') => frame_speed_modifier = 1.0f32, // reset tick rate
-                pancurses::Input::Character('c') => frame_speed_modifier *= 2.0f32, // speed up tick rate
+                Some(Control::SlowDown) => frame_speed_modifier /= 2.0f32, // slowdown tick rate
+                Some(Control::ResetSpeed) => frame_speed_modifier = 1.0f32, // reset tick rate
+                Some(Control::SpeedUp) => frame_speed_modifier *= 2.0f32,  // speed up tick rate
+
+                // movement inputs go through DAS and ARR. They're dropped while the board can't
+                // be seen.
+                Some(control) if !is_too_small => input_handler.press(control),
                 _ => (),
             }
-        };
+        }
 
-        if last_input_handled.elapsed() >= INPUT_POLL_PERIOD {
-            last_input_handled = time::Instant::now();
-            let mut horizontal_motion: i32 = 0;
-            if inputs.move_left {
-                horizontal_motion -= 1;
-            }
-            if inputs.move_right {
-                horizontal_motion += 1;
-            }
-            game_state.move_active_block_horizontal(horizontal_motion);
+        let frame_start = time::Instant::now();
+        frame_pacer.start_frame(frame_start);
+        let frame_time = frame_start - last_frame_start;
+        last_frame_start = frame_start;
+
+        // held keys repeat in real time no matter how fast the game is ticking
+        let mut frame_actions = input_handler.update(frame_time);
+
+        // Nothing moves while the game is paused or can't be seen. Held keys keep timing out in
+        // the meantime but what they did is thrown away, so DAS doesn't fire a burst of shifts on
+        // unpause. Soft drop is kept so letting go of it while paused still stops it.
+        if game_paused || is_too_small {
+            frame_actions
+                .retain(|action| matches!(action, Action::SoftDropStart | Action::SoftDropStop));
+            input_handler.block_changed();
+        }
 
-            let mut relative_rotation: i32 = 0;
-            if inputs.rot_left {
-                relative_rotation -= 1;
-            }
-            if inputs.rot_right {
-                relative_rotation += 1;
+        // rotate and hold keys still held while the next block waits to spawn apply to it
+        if game_state.active_block().is_none() && !game_paused && !is_too_small {
+            for action in input_handler.held_spawn_actions() {
+                if !frame_actions.contains(&action) {
+                    frame_actions.push(action);
+                }
             }
-            game_state.rotate_block(relative_rotation);
+        }
 
-            if inputs.drop {
-                game_state.quick_drop();
-            }
+        // the game holds still while the screen is too small to show it
+        let steps = if game_paused || is_too_small {
+            0
+        } else {
+            timestep.advance(frame_time.mul_f32(frame_speed_modifier))
+        };
 
-            inputs = Inputs {
-                move_left: false,
-                move_right: false,
-                rot_left: false,
-                rot_right: false,
-                drop: false,
-            };
+        // Apply this frame's inputs and step the game state, recording both for the replay.
+        // Frames which don't step the game (while paused or slowed down) only record inputs.
+        let was_game_over = game_state.is_game_over();
+        for action in &frame_actions {
+            action.apply(&mut game_state);
         }
 
-        // Tick the game state
-        if !game_paused {
-            game_state.update(delta_time);
+        if !was_game_over && steps == 0 {
+            replay.record_frame(&frame_actions, None);
         }
 
-        // Render the next frame
-        window.erase();
-
-        // Render the tetris title
-        draw_frame(&window, &title_rect);
-        draw_text_centered(&window, TITLE, title_rect.center_x(), title_rect.center_y());
-
-        // Render next piece preview
-        draw_text_centered(
-            &window,
-            "Next",
-            preview_frame_rect.center_x(),
-            preview_frame_rect.top - 1,
-        );
-        draw_frame(&window, &preview_frame_rect);
-        render_block(
-            &window,
-            Vec2::zero(),
-            preview_rect.left,
-            preview_rect.top,
-            game_state.preview_block(),
-        );
-
-        // Render the score pane
-        draw_text_centered(
-            &window,
-            &format!("Level: {:05}", game_state.level()),
-            score_frame_rect.center_x(),
-            score_frame_rect.center_y() - 1,
-        );
-        draw_text_centered(
-            &window,
-            &format!("Score: {:05}", game_state.score()),
-            score_frame_rect.center_x(),
-            score_frame_rect.center_y(),
-        );
-        draw_frame(&window, &score_frame_rect);
-
-        // Render the board frame
-        draw_frame(&window, &board_frame_rect);
-
-        // Render the active piece
-        if let Some((block, block_pos)) = game_state.active_block() {
-            // TOOD: mayhaps refactor this into its own helper?
-            // render the active piece's drop trail
-            for cell in &block.cells() {
-                let start_row = cell.y + block_pos.y;
-                let col = cell.x + block_pos.x;
-                for row in start_row..board_rect.height {
-                    window.mvaddch(row + board_rect.top, col + board_rect.left, '-');
-                }
+        let was_waiting_to_spawn = game_state.active_block().is_none();
+        for step in 0..steps {
+            if !was_game_over {
+                let step_actions = if step == 0 { &frame_actions[..] } else { &[] };
+                replay.record_frame(step_actions, Some(timestep.step()));
             }
+            game_state.update(timestep.step());
+        }
 
-            render_block(&window, block_pos, board_rect.left, board_rect.top, block);
+        if was_waiting_to_spawn && game_state.active_block().is_some() {
+            input_handler.block_changed();
         }
 
-        // Render the settled pieces
-        game_state.for_each_settled_piece(|block_type: BlockType, cell_pos: Vec2| {
-            render_cell(
-                &window,
-                cell_pos,
-                board_rect.left,
-                board_rect.top,
-                block_type,
-            );
-        });
+        take_line_clear_banner(&mut game_state, &mut line_clear_banner);
+
+        // Render the next frame
+        render_game(
+            renderer,
+            &layout,
+            &game_state,
+            seed,
+            visible_line_clear_banner(&line_clear_banner),
+            game_paused,
+        );
+
+        if show_fps {
+            render_frame_stats(renderer, &frame_stats);
+        }
 
-        // If the game is over, render the game over text
+        // Leave the game over text up for a few seconds before leaving the game
         if game_state.is_game_over() {
             const GAME_OVER_DURATION: time::Duration = time::Duration::from_secs(3);
             match game_over_blit_timer {
@@ -487,142 +579,184 @@ fn run_game(window: &pancurses::Window) -> Option<Screen> {
                     }
                 }
             }
-
-            window.attron(pancurses::A_BLINK);
-            draw_text_centered(
-                &window,
-                "Game Over",
-                board_rect.center_x(),
-                board_rect.center_y(),
-            );
-            window.attroff(pancurses::A_BLINK);
-        }
-        // If the game is paused, render pause text
-        else if game_paused {
-            window.attron(pancurses::A_BLINK);
-            draw_text_centered(
-                &window,
-                "PAUSE",
-                board_rect.center_x(),
-                board_rect.center_y(),
-            );
-            window.attroff(pancurses::A_BLINK);
         }
 
-        window.refresh();
+        renderer.present();
+        frame_stats.record_frame(frame_start, frame_start.elapsed());
     }
 
-    Some(Screen::LeaderboardUpdate(game_state.score()))
+    // NOTE: losing the replay isn't worth interrupting the player over
+    replay.save(REPLAY_FILE_NAME).ok();
+
+    match mode {
+        GameMode::Marathon | GameMode::Ultra { .. } => {
+            Some(Screen::LeaderboardUpdate(mode, game_state.score(), seed))
+        }
+        // only finished sprints earn a time on the leaderboard
+        GameMode::Sprint { .. } => {
+            if game_state.game_over_reason() == Some(GameOverReason::GoalComplete) {
+                let final_time = game_state.elapsed_time().as_millis() as usize;
+                Some(Screen::LeaderboardUpdate(mode, final_time, seed))
+            } else {
+                Some(Screen::Leaderboard(mode))
+            }
+        }
+    }
 }
 
-fn display_leaderboard(
-    window: &pancurses::Window,
-    leaderboard: &Leaderboard,
-    skip_entry: Option<usize>,
-) {
-    let leaderboard_rect = {
-        //      Leaderboard
-        //
-        // #00      FML      00000
-        //
-        // #01      FML      00000
-        // ...
-        // #10      FML      00000
-        const LEADERBOARD_ENTRY_WIDTH: i32 = 21;
-        const LEADERBOARD_HEIGHT: i32 = (2 * (Leaderboard::max_entries() + 1) - 1) as i32;
-
-        let (window_height, window_width) = window.get_max_yx();
-        Rect {
-            left: (window_width - LEADERBOARD_ENTRY_WIDTH) / 2,
-            top: (window_height - LEADERBOARD_HEIGHT) / 2,
-            width: LEADERBOARD_ENTRY_WIDTH,
-            height: LEADERBOARD_HEIGHT,
+// Plays back the last game recorded by run_game
+fn run_replay(
+    renderer: &mut dyn Renderer,
+    input: &mut dyn InputSource,
+    config: &Config,
+) -> Option<Screen> {
+    let replay = match Replay::load(REPLAY_FILE_NAME) {
+        Ok(replay) => replay,
+        Err(_) => {
+            render_message(renderer, "No replay to watch yet");
+            renderer.present();
+            std::thread::sleep(time::Duration::from_secs(2));
+            return Some(Screen::StartMenu);
         }
     };
 
-    let leaderboard_frame_rect = Rect {
-        left: leaderboard_rect.left - 1,
-        top: leaderboard_rect.top - 1,
-        width: leaderboard_rect.width + 2,
-        height: leaderboard_rect.height + 2,
-    };
+    // replays are shown with the player's own next queue length
+    let mut game_state = replay
+        .game_builder()
+        .next_queue_len(config.display.next_queue_len)
+        .build();
+    let (window_height, window_width) = renderer.size();
+    let mut layout = GameLayout::new(window_height, window_width, game_state.next_queue_len());
+    let mut frames = replay.frames().iter().peekable();
+    let mut line_clear_banner = Option::<(String, time::Instant)>::None;
+
+    let mut playback_speed: u32 = 1;
+    let mut is_paused = false;
+    // how much recorded time is waiting to be played back
+    let mut playback_time = time::Duration::from_millis(0);
+    let mut frame_pacer = FramePacer::new(config.display.frame_rate);
+    let mut last_frame_start = time::Instant::now();
+    let mut end_of_replay_timer = Option::<time::Instant>::None;
+
+    'replay_loop: loop {
+        let mut step_frame = false;
+        while let Some(event) = wait_for_frame_input(
+            input,
+            &config.key_bindings,
+            ControlContext::Replay,
+            &frame_pacer,
+        ) {
+            match event {
+                InputEvent::Control(Control::Quit) => break 'replay_loop,
+                InputEvent::Control(Control::Pause) => is_paused = !is_paused,
+                InputEvent::Control(Control::ReplaySpeed1x) => playback_speed = 1,
+                InputEvent::Control(Control::ReplaySpeed2x) => playback_speed = 2,
+                InputEvent::Control(Control::ReplaySpeed4x) => playback_speed = 4,
+                InputEvent::Control(Control::ReplayStep) => step_frame = true,
+                InputEvent::Resize => {
+                    let (window_height, window_width) = renderer.size();
+                    layout =
+                        GameLayout::new(window_height, window_width, game_state.next_queue_len());
+                }
+                _ => (),
+            }
+        }
 
-    draw_frame(&window, &leaderboard_frame_rect);
-    draw_text_centered(
-        &window,
-        "Leaderboard",
-        leaderboard_rect.center_x(),
-        leaderboard_rect.top,
-    );
-
-    for i in 0..Leaderboard::max_entries() {
-        let entry = leaderboard.entry(i);
-        let (name, score) = entry
-            .map(|e| (e.name.as_ref(), e.score))
-            .unwrap_or(("---", 0));
-
-        let mut leaderboard_pos = i + 1;
-        if skip_entry.is_some() && skip_entry.unwrap() <= i {
-            leaderboard_pos += 1;
-        }
-
-        if leaderboard_pos <= Leaderboard::max_entries() {
-            let row_offset = (leaderboard_pos * 2) as i32;
-
-            draw_text_centered(
-                &window,
-                &format!("#{:02}    {:3}    {:05}", leaderboard_pos, name, score),
-                leaderboard_rect.center_x(),
-                leaderboard_rect.top + row_offset,
-            );
+        let frame_start = time::Instant::now();
+        frame_pacer.start_frame(frame_start);
+        let elapsed_time = frame_start - last_frame_start;
+        last_frame_start = frame_start;
+
+        if is_screen_too_small(renderer) {
+            // playback holds still while the screen is too small to show it
+        } else if is_paused {
+            // stepping plays exactly one recorded frame no matter how long it was
+            if step_frame {
+                if let Some(frame) = frames.next() {
+                    frame.apply(&mut game_state);
+                }
+            }
+        } else {
+            playback_time += elapsed_time * playback_speed;
+            while let Some(frame) = frames.peek() {
+                let frame_time = frame.delta_time.unwrap_or_default();
+                if frame_time > playback_time {
+                    break;
+                }
+
+                playback_time -= frame_time;
+                frame.apply(&mut game_state);
+                frames.next();
+            }
         }
+
+        take_line_clear_banner(&mut game_state, &mut line_clear_banner);
+        render_game(
+            renderer,
+            &layout,
+            &game_state,
+            replay.seed(),
+            visible_line_clear_banner(&line_clear_banner),
+            is_paused,
+        );
+
+        // Render the playback controls below the board
+        let playback_status = if frames.peek().is_none() {
+            "End of replay".to_string()
+        } else {
+            format!("Replay {}x", playback_speed)
+        };
+        render_replay_status(renderer, &layout, &playback_status);
+
+        // Leave the end of the replay up for a few seconds before going back to the menu
+        if frames.peek().is_none() {
+            const END_OF_REPLAY_DURATION: time::Duration = time::Duration::from_secs(3);
+            match end_of_replay_timer {
+                None => end_of_replay_timer = Some(time::Instant::now()),
+                Some(timer) => {
+                    if timer.elapsed() > END_OF_REPLAY_DURATION {
+                        break;
+                    }
+                }
+            }
+        }
+
+        renderer.present();
     }
+
+    Some(Screen::StartMenu)
 }
 
-fn run_leaderboard_update(window: &pancurses::Window, score: usize) -> Option<Screen> {
+fn run_leaderboard_update(
+    renderer: &mut dyn Renderer,
+    input: &mut dyn InputSource,
+    bindings: &KeyBindings,
+    mode: GameMode,
+    score: usize,
+    seed: u64,
+) -> Option<Screen> {
     let mut leaderboard = {
-        let leaderboard_from_file = Leaderboard::load(LEADERBOARD_FILE_NAME);
-        leaderboard_from_file.unwrap_or(Leaderboard::new())
+        let leaderboard_from_file = Leaderboard::load(leaderboard_file_name(mode));
+        leaderboard_from_file.unwrap_or_default()
     };
 
-    let new_leaderboard_entry_pos = leaderboard.get_place_on_leaderboard(score);
-    if new_leaderboard_entry_pos.is_some() {
-        let leaderboard_rect = {
-            //      Leaderboard
-            //
-            // #00      FML      00000
-            //
-            // #01      FML      00000
-            // ...
-            // #10      FML      00000
-            const LEADERBOARD_ENTRY_WIDTH: i32 = 21;
-            const LEADERBOARD_HEIGHT: i32 = (2 * (Leaderboard::max_entries() + 1) - 1) as i32;
-
-            let (window_height, window_width) = window.get_max_yx();
-            Rect {
-                left: (window_width - LEADERBOARD_ENTRY_WIDTH) / 2,
-                top: (window_height - LEADERBOARD_HEIGHT) / 2,
-                width: LEADERBOARD_ENTRY_WIDTH,
-                height: LEADERBOARD_HEIGHT,
-            }
-        };
-
+    let order = leaderboard_order(mode);
+    if let Some(new_leaderboard_entry_pos) = leaderboard.get_place_on_leaderboard(score, order) {
         let mut next_initial = 0;
         let mut initials = ['_'; 3];
 
         loop {
-            if let Some(input) = window.getch() {
-                match input {
-                    // check for movement inputs
-                    pancurses::Input::Character(ASCII_ENTER) | pancurses::Input::KeyEnter => break,
-                    pancurses::Input::Character(ASCII_BACKSPACE)
-                    | pancurses::Input::Character(ASCII_DEL)
-                    | pancurses::Input::KeyBackspace
-                    | pancurses::Input::KeyDC => {
+            // the initials are typed in so every key comes through as it is
+            if let Some(InputEvent::Key(key)) =
+                wait_for_menu_input(input, bindings, ControlContext::Text)
+            {
+                match key {
+                    Key::Enter => break,
+                    Key::Backspace => {
                         next_initial = std::cmp::max(1, next_initial) - 1;
                         initials[next_initial] = '_';
                     }
-                    pancurses::Input::Character(letter) => {
+                    Key::Char(letter) => {
                         if next_initial < initials.len() {
                             initials[next_initial] = letter;
                             next_initial += 1;
@@ -632,75 +766,280 @@ fn run_leaderboard_update(window: &pancurses::Window, score: usize) -> Option<Sc
                 }
             };
 
-            window.erase();
-            display_leaderboard(&window, &leaderboard, new_leaderboard_entry_pos);
-
-            let leaderboard_pos = new_leaderboard_entry_pos.unwrap() + 1;
-            let row_offset = (leaderboard_pos * 2) as i32;
-
-            // Render the new entry WIP space
-            window.attron(pancurses::A_BLINK);
-            draw_text_centered(
-                &window,
-                &format!(
-                    "#{:02}    {}{}{}    {:05}",
-                    leaderboard_pos, initials[0], initials[1], initials[2], score
-                ),
-                leaderboard_rect.center_x(),
-                leaderboard_rect.top + row_offset,
+            let initials_text = initials.iter().collect::<String>();
+            render_leaderboard(
+                renderer,
+                mode,
+                &leaderboard,
+                Some(&PendingEntry {
+                    place: new_leaderboard_entry_pos,
+                    initials: &initials_text,
+                    score,
+                    seed,
+                }),
             );
-            window.attroff(pancurses::A_BLINK);
-
-            window.refresh();
+            renderer.present();
         }
 
         let name = initials
             .iter()
             .map(|initial| if *initial == '_' { ' ' } else { *initial })
             .collect::<String>();
-        leaderboard.add_score(name, score);
-        leaderboard.save(LEADERBOARD_FILE_NAME);
+        leaderboard.add_entry(LeaderboardEntry::new(name, score).with_seed(seed), order);
+        leaderboard.save(leaderboard_file_name(mode));
     }
 
-    Some(Screen::Leaderboard)
+    Some(Screen::Leaderboard(mode))
 }
 
-fn run_leaderboard_display(window: &pancurses::Window) -> Option<Screen> {
+fn run_leaderboard_display(
+    renderer: &mut dyn Renderer,
+    input: &mut dyn InputSource,
+    bindings: &KeyBindings,
+    mode: GameMode,
+) -> Option<Screen> {
     let leaderboard = {
-        let leaderboard_from_file = Leaderboard::load(LEADERBOARD_FILE_NAME);
-        leaderboard_from_file.unwrap_or(Leaderboard::new())
+        let leaderboard_from_file = Leaderboard::load(leaderboard_file_name(mode));
+        leaderboard_from_file.unwrap_or_default()
     };
 
-    window.erase();
-    display_leaderboard(&window, &leaderboard, None);
-    window.refresh();
-    std::thread::sleep(std::time::Duration::from_secs(3));
+    // keep drawing while the leaderboard is up in case the screen is resized
+    const LEADERBOARD_DISPLAY_DURATION: time::Duration = time::Duration::from_secs(3);
+    let display_start = time::Instant::now();
+    while display_start.elapsed() < LEADERBOARD_DISPLAY_DURATION {
+        render_leaderboard(renderer, mode, &leaderboard, None);
+        renderer.present();
+        wait_for_menu_input(input, bindings, ControlContext::Menu);
+    }
 
     Some(Screen::StartMenu)
 }
 
+// The config lives in the user's config directory, e.g. ~/.config/tetrust/config.toml
+fn config_file_name() -> Option<String> {
+    let config_dir = std::env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var("HOME")
+                .ok()
+                .map(|home| std::path::Path::new(&home).join(".config"))
+        })
+        .or_else(|| std::env::var("APPDATA").ok().map(std::path::PathBuf::from))?;
+
+    Some(
+        config_dir
+            .join("tetrust")
+            .join("config.toml")
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+// Lists every control and its keys. Selecting a control waits for a key to add to its bindings,
+// or to take away if it's already one of them.
+fn run_settings(
+    renderer: &mut dyn Renderer,
+    input: &mut dyn InputSource,
+    config: &mut Config,
+    config_file: Option<&str>,
+) -> Option<Screen> {
+    // the controls are followed by options to reset them all and to go back
+    let option_count = Control::ALL.len() + 2;
+    let original_config = config.clone();
+    let mut menu_cursor = 0;
+    let mut rebinding = false;
+    let mut message = String::new();
+
+    loop {
+        // Input handling
+        if rebinding {
+            // any key can be bound, including the menu keys
+            if let Some(InputEvent::Key(key)) =
+                wait_for_menu_input(input, &config.key_bindings, ControlContext::Text)
+            {
+                let control = Control::ALL[menu_cursor];
+                let was_bound = config.key_bindings.keys(control).contains(&key);
+                message = match config.key_bindings.toggle_key(control, key) {
+                    Ok(()) if was_bound => {
+                        format!("{} is no longer bound to {}", control.label(), key)
+                    }
+                    Ok(()) => format!("{} is now also bound to {}", control.label(), key),
+                    Err(e) => e,
+                };
+                rebinding = false;
+            }
+        } else {
+            match wait_for_menu_input(input, &config.key_bindings, ControlContext::Menu) {
+                Some(InputEvent::Control(Control::MenuSelect)) => {
+                    if menu_cursor < Control::ALL.len() {
+                        rebinding = true;
+                        message = format!(
+                            "Press a key to add to or remove from {}",
+                            Control::ALL[menu_cursor].label()
+                        );
+                    } else if menu_cursor == Control::ALL.len() {
+                        config.key_bindings = KeyBindings::default();
+                        message = String::from("Restored the default keys");
+                    } else {
+                        break;
+                    }
+                }
+                Some(InputEvent::Control(control)) => {
+                    menu_cursor = move_menu_cursor(menu_cursor, option_count, control)
+                }
+                _ => (),
+            }
+        }
+
+        // Render the controls and their keys
+        render_settings(renderer, &config.key_bindings, menu_cursor, &message);
+        renderer.present();
+    }
+
+    // only write the config once something has changed so the defaults can keep evolving
+    if *config != original_config {
+        if let Some(config_file) = config_file {
+            if let Err(e) = config.save(config_file) {
+                render_message(renderer, &e);
+                renderer.present();
+                std::thread::sleep(time::Duration::from_secs(2));
+            }
+        }
+    }
+
+    Some(Screen::StartMenu)
+}
+
+// usage: tetrust [--seed <seed> | --daily]
+fn parse_seed_choice(args: &[String]) -> Result<SeedChoice, String> {
+    const USAGE: &str = "usage: tetrust [--seed <seed> | --daily]";
+
+    match args {
+        [] => Ok(SeedChoice::Random),
+        [flag] if flag == "--daily" => Ok(SeedChoice::Daily),
+        [flag, seed] if flag == "--seed" => seed
+            .parse::<u64>()
+            .map(SeedChoice::Fixed)
+            .map_err(|e| format!("bad seed '{}' ({})\n{}", seed, e, USAGE)),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn parse_mode(mode_name: &str) -> Option<GameMode> {
+    match mode_name {
+        "marathon" => Some(GameMode::Marathon),
+        "sprint" => Some(GameMode::sprint()),
+        "ultra" => Some(GameMode::ultra()),
+        _ => None,
+    }
+}
+
+// Plays a game from a seed and an input script without opening a terminal window
+// usage: tetrust simulate <seed> <script file> [marathon|sprint|ultra]
+fn run_simulator(args: &[String]) -> Result<(), String> {
+    const USAGE: &str = "usage: tetrust simulate <seed> <script file> [marathon|sprint|ultra]";
+
+    let (seed, script_file_name, mode_name) = match args {
+        [seed, script_file_name] => (seed, script_file_name, "marathon"),
+        [seed, script_file_name, mode_name] => (seed, script_file_name, mode_name.as_str()),
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let seed = seed
+        .parse::<u64>()
+        .map_err(|e| format!("bad seed '{}' ({})\n{}", seed, e, USAGE))?;
+    let mode =
+        parse_mode(mode_name).ok_or_else(|| format!("unknown mode '{}'\n{}", mode_name, USAGE))?;
+    let script = InputScript::load(script_file_name)?;
+
+    let result = run_simulation(seed, mode, &script);
+    println!("Score: {}", result.score);
+    println!("Lines: {}", result.lines);
+    println!("Level: {}", result.level);
+    if let Some(game_over_reason) = result.game_over_reason {
+        println!("Game over: {:?}", game_over_reason);
+    }
+    println!("{}", result.board);
+
+    Ok(())
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("simulate") {
+        if let Err(e) = run_simulator(&args[2..]) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let seed_choice = match parse_seed_choice(&args[1..]) {
+        Ok(seed_choice) => seed_choice,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // a bad config is reported before the terminal is taken over so the error stays readable
+    let config_file = config_file_name();
+    let mut config = match &config_file {
+        Some(config_file) => match Config::load(config_file) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        None => Config::default(),
+    };
+
     // setup the window
     let window = pancurses::initscr();
     pancurses::noecho(); // prevent key inputs rendering to the screen
     pancurses::cbreak();
     pancurses::curs_set(0);
     pancurses::set_title(TITLE);
-    window.nodelay(true); // don't block waiting for key inputs (we'll poll)
+    window.nodelay(true); // don't block waiting for key inputs (each screen sets its own timeout)
     window.keypad(true); // let special keys be captured by the program (i.e. esc/backspace/del/arrow keys)
 
     // setup the color system
     Colors::setup();
 
+    let mut renderer = TerminalRenderer::new(&window);
+    let mut input = TerminalInput::new(&window);
+
     // Run the game until we quit
     let mut screen = Screen::StartMenu;
     loop {
         // Run the current screen until it signals a transition
         let next_screen = match screen {
-            Screen::StartMenu => run_start_menu(&window),
-            Screen::Game => run_game(&window),
-            Screen::LeaderboardUpdate(score) => run_leaderboard_update(&window, score),
-            Screen::Leaderboard => run_leaderboard_display(&window),
+            Screen::StartMenu => run_start_menu(&mut renderer, &mut input, &config.key_bindings),
+            Screen::ModeSelect(target) => {
+                run_mode_select(&mut renderer, &mut input, &config.key_bindings, target)
+            }
+            Screen::Game(mode) => run_game(&mut renderer, &mut input, &config, mode, seed_choice),
+            Screen::LeaderboardUpdate(mode, score, seed) => run_leaderboard_update(
+                &mut renderer,
+                &mut input,
+                &config.key_bindings,
+                mode,
+                score,
+                seed,
+            ),
+            Screen::Leaderboard(mode) => {
+                run_leaderboard_display(&mut renderer, &mut input, &config.key_bindings, mode)
+            }
+            Screen::WatchReplay => run_replay(&mut renderer, &mut input, &config),
+            Screen::Settings => run_settings(
+                &mut renderer,
+                &mut input,
+                &mut config,
+                config_file.as_deref(),
+            ),
         };
 
         // If the transition includes a new screen start rendering that.
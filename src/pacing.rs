@@ -0,0 +1,206 @@
+use crate::config::*;
+use crate::game::GameStateBuilder;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How the game is drawn, from the `[display]` table of a config file:
+///
+/// ```text
+/// [display]
+/// frame_rate = 60
+/// show_fps = true
+/// next_queue_len = 3
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Display {
+    /// frames drawn, and game updates run, per second
+    pub frame_rate: u32,
+    /// whether to show the frame rate and frame time counter from the start of each game
+    pub show_fps: bool,
+    /// how many upcoming blocks are shown, from 3 to 6
+    pub next_queue_len: usize,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Display {
+            frame_rate: 60,
+            show_fps: false,
+            next_queue_len: GameStateBuilder::DEFAULT_NEXT_QUEUE_LEN,
+        }
+    }
+}
+
+impl Display {
+    const MAX_FRAME_RATE: u32 = 1000;
+    const MIN_NEXT_QUEUE_LEN: usize = 3;
+    const MAX_NEXT_QUEUE_LEN: usize = 6;
+
+    /// Reads the `[display]` table of a config file, starting from the defaults
+    pub fn parse<S: AsRef<str>>(config: S) -> Result<Self, String> {
+        let mut display = Display::default();
+        parse_table(config.as_ref(), "display", |name, value| {
+            match name {
+                "frame_rate" => {
+                    display.frame_rate = value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|frame_rate| (1..=Self::MAX_FRAME_RATE).contains(frame_rate))
+                        .ok_or_else(|| {
+                            format!(
+                                "frame_rate must be from 1 to {} but was {}",
+                                Self::MAX_FRAME_RATE,
+                                value
+                            )
+                        })?
+                }
+                "show_fps" => {
+                    display.show_fps = value
+                        .parse::<bool>()
+                        .map_err(|_| format!("show_fps must be true or false but was {}", value))?
+                }
+                "next_queue_len" => {
+                    display.next_queue_len = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|next_queue_len| {
+                            (Self::MIN_NEXT_QUEUE_LEN..=Self::MAX_NEXT_QUEUE_LEN)
+                                .contains(next_queue_len)
+                        })
+                        .ok_or_else(|| {
+                            format!(
+                                "next_queue_len must be from {} to {} but was {}",
+                                Self::MIN_NEXT_QUEUE_LEN,
+                                Self::MAX_NEXT_QUEUE_LEN,
+                                value
+                            )
+                        })?
+                }
+                _ => return Err(format!("unknown display setting '{}'", name)),
+            }
+            Ok(())
+        })?;
+
+        Ok(display)
+    }
+
+    pub fn to_config_string(&self) -> String {
+        format!(
+            "[display]\nframe_rate = {}\nshow_fps = {}\nnext_queue_len = {}\n",
+            self.frame_rate, self.show_fps, self.next_queue_len
+        )
+    }
+}
+
+/// Paces a loop to a fixed frame rate so it can sleep between frames instead of spinning
+pub struct FramePacer {
+    frame_time: Duration,
+    next_frame: Option<Instant>,
+}
+
+impl FramePacer {
+    // Frames this far behind are dropped rather than rushed through to catch up
+    const MAX_FRAMES_BEHIND: u32 = 4;
+
+    pub fn new(frame_rate: u32) -> Self {
+        assert!(frame_rate > 0);
+        FramePacer {
+            // whole microseconds so replays store the frame time exactly
+            frame_time: Duration::from_micros(1_000_000 / frame_rate as u64),
+            next_frame: None,
+        }
+    }
+
+    pub fn frame_time(&self) -> Duration {
+        self.frame_time
+    }
+
+    /// How long to wait before starting the next frame. The first frame is due straight away.
+    pub fn time_until_next_frame(&self, now: Instant) -> Duration {
+        match self.next_frame {
+            Some(next_frame) if next_frame > now => next_frame - now,
+            _ => Duration::from_millis(0),
+        }
+    }
+
+    /// Starts a frame and schedules the next one a frame time after this one was due
+    pub fn start_frame(&mut self, now: Instant) {
+        let next_frame = match self.next_frame {
+            Some(next_frame) if now < next_frame + self.frame_time * Self::MAX_FRAMES_BEHIND => {
+                next_frame + self.frame_time
+            }
+            _ => now + self.frame_time,
+        };
+        self.next_frame = Some(next_frame);
+    }
+}
+
+/// Splits game time into fixed steps so the game updates the same way however the frames land
+pub struct FixedTimestep {
+    step: Duration,
+    accumulated_time: Duration,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Duration) -> Self {
+        assert!(step > Duration::from_millis(0));
+        FixedTimestep {
+            step,
+            accumulated_time: Duration::from_millis(0),
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Adds game time and returns how many whole steps are ready to run
+    pub fn advance(&mut self, delta_time: Duration) -> u32 {
+        self.accumulated_time += delta_time;
+        let steps = (self.accumulated_time.as_nanos() / self.step.as_nanos()) as u32;
+        self.accumulated_time -= self.step * steps;
+        steps
+    }
+}
+
+/// The frame rate and how long each frame took to run over the last second
+#[derive(Default)]
+pub struct FrameStats {
+    // when each frame started and how long its work took
+    frames: VecDeque<(Instant, Duration)>,
+}
+
+impl FrameStats {
+    const WINDOW: Duration = Duration::from_secs(1);
+
+    pub fn new() -> Self {
+        FrameStats {
+            frames: VecDeque::new(),
+        }
+    }
+
+    pub fn record_frame(&mut self, frame_start: Instant, work_time: Duration) {
+        self.frames.push_back((frame_start, work_time));
+        while let Some((oldest_frame_start, _)) = self.frames.front() {
+            if frame_start.duration_since(*oldest_frame_start) < Self::WINDOW {
+                break;
+            }
+            self.frames.pop_front();
+        }
+    }
+
+    /// Frames started in the last second
+    pub fn fps(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The average time spent running each frame, not counting the time spent waiting for it
+    pub fn average_work_time(&self) -> Duration {
+        if self.frames.is_empty() {
+            return Duration::from_millis(0);
+        }
+
+        let total_work_time: Duration = self.frames.iter().map(|(_, work_time)| *work_time).sum();
+        total_work_time / self.frames.len() as u32
+    }
+}
@@ -0,0 +1,130 @@
+use crate::block::*;
+use snm_rand_utils::range_rng::*;
+
+/// Produces the sequence of tetrominoes which will be fed to the game board.
+pub trait PieceGenerator {
+    fn next_piece(&mut self) -> BlockType;
+}
+
+/// The built-in generators
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PieceGeneratorType {
+    Memoryless,
+    Bag7,
+    Bag14,
+    History,
+}
+
+impl PieceGeneratorType {
+    pub fn create(&self, rng: Box<dyn RangeRng<usize>>) -> Box<dyn PieceGenerator> {
+        match *self {
+            PieceGeneratorType::Memoryless => Box::new(MemorylessGenerator::new(rng)),
+            PieceGeneratorType::Bag7 => Box::new(BagGenerator::new(1, rng)),
+            PieceGeneratorType::Bag14 => Box::new(BagGenerator::new(2, rng)),
+            PieceGeneratorType::History => Box::new(HistoryGenerator::new(rng)),
+        }
+    }
+}
+
+/// The classic generator: every piece is pulled independently from the full set of block types.
+pub struct MemorylessGenerator {
+    rng: Box<dyn RangeRng<usize>>,
+}
+
+impl MemorylessGenerator {
+    pub fn new(rng: Box<dyn RangeRng<usize>>) -> Self {
+        MemorylessGenerator { rng }
+    }
+}
+
+impl PieceGenerator for MemorylessGenerator {
+    fn next_piece(&mut self) -> BlockType {
+        BLOCKTYPES[self.rng.gen_range(1, BLOCKTYPES.len() + 1) - 1]
+    }
+}
+
+/// The guideline "bag" generator: a bag holding N copies of each block type is drawn from without
+/// replacement and refilled once it's empty. With 1 copy (the 7-bag) an I piece is never more than
+/// 12 pieces away from the last one.
+pub struct BagGenerator {
+    copies_per_bag: usize,
+    bag: Vec<BlockType>,
+    rng: Box<dyn RangeRng<usize>>,
+}
+
+impl BagGenerator {
+    pub fn new(copies_per_bag: usize, rng: Box<dyn RangeRng<usize>>) -> Self {
+        assert!(copies_per_bag > 0);
+        BagGenerator {
+            copies_per_bag,
+            bag: Vec::with_capacity(copies_per_bag * BLOCKTYPES.len()),
+            rng,
+        }
+    }
+
+    fn refill(&mut self) {
+        for _ in 0..self.copies_per_bag {
+            self.bag.extend_from_slice(&BLOCKTYPES);
+        }
+    }
+}
+
+impl PieceGenerator for BagGenerator {
+    fn next_piece(&mut self) -> BlockType {
+        if self.bag.is_empty() {
+            self.refill();
+        }
+
+        let pick = self.rng.gen_range(1, self.bag.len() + 1) - 1;
+        self.bag.remove(pick)
+    }
+}
+
+/// The TGM-style generator: remembers the last few pieces dealt and rerolls a bounded number of
+/// times when the roll matches one of them. The first piece is never an S, Z or O so the player
+/// never starts the game with an overhang.
+pub struct HistoryGenerator {
+    history: [BlockType; HistoryGenerator::HISTORY_LEN],
+    is_first_piece: bool,
+    rng: Box<dyn RangeRng<usize>>,
+}
+
+impl HistoryGenerator {
+    const HISTORY_LEN: usize = 4;
+    const MAX_ROLLS: usize = 4;
+    const FIRST_PIECES: [BlockType; 4] = [BlockType::I, BlockType::T, BlockType::J, BlockType::L];
+
+    pub fn new(rng: Box<dyn RangeRng<usize>>) -> Self {
+        HistoryGenerator {
+            history: [BlockType::Z, BlockType::Z, BlockType::S, BlockType::S],
+            is_first_piece: true,
+            rng,
+        }
+    }
+
+    fn roll(&mut self, choices: &[BlockType]) -> BlockType {
+        choices[self.rng.gen_range(1, choices.len() + 1) - 1]
+    }
+}
+
+impl PieceGenerator for HistoryGenerator {
+    fn next_piece(&mut self) -> BlockType {
+        let piece = if self.is_first_piece {
+            self.is_first_piece = false;
+            self.roll(&Self::FIRST_PIECES)
+        } else {
+            let mut piece = self.roll(&BLOCKTYPES);
+            for _ in 1..Self::MAX_ROLLS {
+                if !self.history.contains(&piece) {
+                    break;
+                }
+                piece = self.roll(&BLOCKTYPES);
+            }
+            piece
+        };
+
+        self.history.rotate_left(1);
+        self.history[Self::HISTORY_LEN - 1] = piece;
+        piece
+    }
+}
@@ -0,0 +1,170 @@
+use crate::block::*;
+use crate::util::*;
+
+/// Which colors something is drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Default,
+    /// the colors of a block type's cells
+    Block(BlockType),
+    /// one of the title card's colors. Indexes wrap around the palette.
+    Menu(usize),
+}
+
+/// How a character or some text is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub color: Color,
+    pub blink: bool,
+    pub dim: bool,
+}
+
+impl Style {
+    pub const PLAIN: Style = Style {
+        color: Color::Default,
+        blink: false,
+        dim: false,
+    };
+
+    pub fn color(color: Color) -> Self {
+        Style {
+            color,
+            ..Style::PLAIN
+        }
+    }
+
+    pub fn blink(self) -> Self {
+        Style {
+            blink: true,
+            ..self
+        }
+    }
+
+    pub fn dim(self) -> Self {
+        Style { dim: true, ..self }
+    }
+}
+
+/// Somewhere the screens can be drawn to. The terminal front end draws with curses and tests
+/// draw into a [`GridRenderer`]. Drawing outside of the screen is allowed and ignored.
+pub trait Renderer {
+    /// The size of the screen in characters as (height, width), the same order curses uses
+    fn size(&self) -> (i32, i32);
+
+    /// Blanks the whole screen
+    fn clear(&mut self);
+
+    fn draw_char(&mut self, x: i32, y: i32, ch: char, style: Style);
+
+    fn draw_text(&mut self, x: i32, y: i32, text: &str, style: Style) {
+        for (i, ch) in text.chars().enumerate() {
+            self.draw_char(x + i as i32, y, ch, style);
+        }
+    }
+
+    /// Draws a box around the edge of the rect
+    fn draw_frame(&mut self, rect: &Rect) {
+        let left = rect.left;
+        let top = rect.top;
+        let right = rect.right();
+        let bottom = rect.bottom();
+
+        // draw corners
+        for (x, y) in [(left, top), (right, top), (left, bottom), (right, bottom)].iter() {
+            self.draw_char(*x, *y, '+', Style::PLAIN);
+        }
+
+        // draw horizontal borders
+        for col in left + 1..right {
+            self.draw_char(col, top, '-', Style::PLAIN);
+            self.draw_char(col, bottom, '-', Style::PLAIN);
+        }
+
+        // draw vertical borders
+        for row in top + 1..bottom {
+            self.draw_char(left, row, '|', Style::PLAIN);
+            self.draw_char(right, row, '|', Style::PLAIN);
+        }
+    }
+
+    /// Shows everything drawn since the last present
+    fn present(&mut self);
+
+    fn draw_text_centered(&mut self, text: &str, x_center: i32, y_center: i32, style: Style) {
+        self.draw_text(x_center - (text.len() / 2) as i32, y_center, text, style);
+    }
+}
+
+/// Draws into a grid of characters in memory, for snapshot tests of whole screens
+pub struct GridRenderer {
+    width: i32,
+    height: i32,
+    cells: Vec<(char, Style)>,
+    clipped_chars: usize,
+}
+
+impl GridRenderer {
+    pub fn new(width: i32, height: i32) -> Self {
+        GridRenderer {
+            width,
+            height,
+            cells: vec![(' ', Style::PLAIN); (width * height) as usize],
+            clipped_chars: 0,
+        }
+    }
+
+    /// The character and style at a position or `None` if it's off the screen
+    pub fn cell(&self, x: i32, y: i32) -> Option<(char, Style)> {
+        self.index(x, y).map(|index| self.cells[index])
+    }
+
+    /// How many characters have been drawn off the edge of the screen, which means something
+    /// didn't fit
+    pub fn clipped_chars(&self) -> usize {
+        self.clipped_chars
+    }
+
+    /// Every row of the screen with trailing spaces trimmed, joined with newlines
+    pub fn to_text(&self) -> String {
+        self.cells
+            .chunks(self.width as usize)
+            .map(|row| {
+                row.iter()
+                    .map(|(ch, _)| *ch)
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if (0..self.width).contains(&x) && (0..self.height).contains(&y) {
+            Some((y * self.width + x) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl Renderer for GridRenderer {
+    fn size(&self) -> (i32, i32) {
+        (self.height, self.width)
+    }
+
+    fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            *cell = (' ', Style::PLAIN);
+        }
+    }
+
+    fn draw_char(&mut self, x: i32, y: i32, ch: char, style: Style) {
+        match self.index(x, y) {
+            Some(index) => self.cells[index] = (ch, style),
+            None => self.clipped_chars += 1,
+        }
+    }
+
+    fn present(&mut self) {}
+}
@@ -0,0 +1,284 @@
+use crate::action::*;
+use crate::game::*;
+use crate::rotation::*;
+use std::time::Duration;
+
+/// Everything which happened during one frame of a recorded game
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplayFrame {
+    /// the actions applied before the update, in the order they were applied
+    pub actions: Vec<Action>,
+    /// how far the game was updated or `None` if the game was paused and didn't update
+    pub delta_time: Option<Duration>,
+}
+
+impl ReplayFrame {
+    /// Plays the frame back onto a game
+    pub fn apply(&self, game_state: &mut GameState) {
+        for action in &self.actions {
+            action.apply(game_state);
+        }
+
+        if let Some(delta_time) = self.delta_time {
+            game_state.update(delta_time);
+        }
+    }
+}
+
+/// A recording of a game which can be played back exactly. Games are fully determined by their
+/// seed, their mode, their rotation system and the inputs and frame times fed to them so that's
+/// all a replay stores.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    seed: u64,
+    mode: GameMode,
+    rotation_system: RotationSystemType,
+    frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    const MAGIC: &'static [u8; 4] = b"TRPL";
+    const VERSION: u8 = 2;
+
+    // flags packed into the low bits of each frame's time
+    const FRAME_HAS_UPDATE: u64 = 0b01;
+    const FRAME_HAS_ACTIONS: u64 = 0b10;
+    const FRAME_FLAG_BITS: u32 = 2;
+
+    /// Starts an empty recording of a standard game (see [`GameStateBuilder::standard`]) played
+    /// with the given rotation system
+    pub fn new(seed: u64, mode: GameMode, rotation_system: RotationSystemType) -> Self {
+        Replay {
+            seed,
+            mode,
+            rotation_system,
+            frames: vec![],
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
+
+    pub fn rotation_system(&self) -> RotationSystemType {
+        self.rotation_system
+    }
+
+    pub fn frames(&self) -> &[ReplayFrame] {
+        &self.frames
+    }
+
+    /// Creates the game the replay was recorded from, before any frames have been played
+    pub fn new_game(&self) -> GameState {
+        self.game_builder().build()
+    }
+
+    /// Sets up the game the replay was recorded from so it can be shown differently. Only the
+    /// length of the next queue can be changed without the replay playing out differently.
+    pub fn game_builder(&self) -> GameStateBuilder {
+        GameStateBuilder::standard(self.mode, self.seed)
+            .rotation_system(self.rotation_system.system())
+    }
+
+    /// Replays store frame times to the microsecond. Games being recorded must be updated with
+    /// rounded frame times so playing them back lands on exactly the same state.
+    pub fn round_frame_time(delta_time: Duration) -> Duration {
+        Duration::from_micros(delta_time.as_micros() as u64)
+    }
+
+    /// Records a frame. Frames which neither update the game nor apply any actions are skipped.
+    pub fn record_frame(&mut self, actions: &[Action], delta_time: Option<Duration>) {
+        if actions.is_empty() && delta_time.is_none() {
+            return;
+        }
+
+        if let Some(delta_time) = delta_time {
+            debug_assert_eq!(delta_time, Self::round_frame_time(delta_time));
+        }
+        self.frames.push(ReplayFrame {
+            actions: actions.to_vec(),
+            delta_time,
+        });
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(Self::MAGIC);
+        bytes.push(Self::VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+
+        let (mode_id, mode_param) = match self.mode {
+            GameMode::Marathon => (0, 0),
+            GameMode::Sprint { line_goal } => (1, line_goal as u64),
+            GameMode::Ultra { time_limit } => (2, time_limit.as_millis() as u64),
+        };
+        bytes.push(mode_id);
+        write_varint(&mut bytes, mode_param);
+
+        let rotation_system_id = RotationSystemType::ALL
+            .iter()
+            .position(|system_type| *system_type == self.rotation_system)
+            .unwrap();
+        bytes.push(rotation_system_id as u8);
+
+        // Most frames are just a frame time so each frame is packed into a varint of its time in
+        // microseconds and a couple of flags, followed by its actions if it has any.
+        for frame in &self.frames {
+            let micros = frame.delta_time.map_or(0, |d| d.as_micros() as u64);
+            let mut header = micros << Self::FRAME_FLAG_BITS;
+            if frame.delta_time.is_some() {
+                header |= Self::FRAME_HAS_UPDATE;
+            }
+            if !frame.actions.is_empty() {
+                header |= Self::FRAME_HAS_ACTIONS;
+            }
+            write_varint(&mut bytes, header);
+
+            if !frame.actions.is_empty() {
+                write_varint(&mut bytes, frame.actions.len() as u64);
+                for action in &frame.actions {
+                    let action_id = Action::ALL.iter().position(|a| a == action).unwrap();
+                    bytes.push(action_id as u8);
+                }
+            }
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = ByteReader { bytes, pos: 0 };
+
+        if reader.read_bytes(Self::MAGIC.len())? != Self::MAGIC {
+            return Err("not a replay file".to_string());
+        }
+
+        let version = reader.read_u8()?;
+        if version != Self::VERSION {
+            return Err(format!("unsupported replay version {}", version));
+        }
+
+        let mut seed_bytes = [0; 8];
+        seed_bytes.copy_from_slice(reader.read_bytes(8)?);
+        let seed = u64::from_le_bytes(seed_bytes);
+
+        let mode_id = reader.read_u8()?;
+        let mode_param = reader.read_varint()?;
+        let mode = match mode_id {
+            0 => GameMode::Marathon,
+            1 => GameMode::Sprint {
+                line_goal: mode_param as usize,
+            },
+            2 => GameMode::Ultra {
+                time_limit: Duration::from_millis(mode_param),
+            },
+            _ => return Err(format!("unknown game mode {}", mode_id)),
+        };
+
+        let rotation_system_id = reader.read_u8()? as usize;
+        let rotation_system = *RotationSystemType::ALL
+            .get(rotation_system_id)
+            .ok_or_else(|| format!("unknown rotation system {}", rotation_system_id))?;
+
+        let mut frames = vec![];
+        while !reader.is_empty() {
+            let header = reader.read_varint()?;
+
+            let mut actions = vec![];
+            if header & Self::FRAME_HAS_ACTIONS != 0 {
+                let action_count = reader.read_varint()?;
+                for _ in 0..action_count {
+                    let action_id = reader.read_u8()? as usize;
+                    let action = Action::ALL
+                        .get(action_id)
+                        .ok_or_else(|| format!("unknown action {}", action_id))?;
+                    actions.push(*action);
+                }
+            }
+
+            let delta_time = if header & Self::FRAME_HAS_UPDATE != 0 {
+                Some(Duration::from_micros(header >> Self::FRAME_FLAG_BITS))
+            } else {
+                None
+            };
+
+            frames.push(ReplayFrame {
+                actions,
+                delta_time,
+            });
+        }
+
+        Ok(Replay {
+            seed,
+            mode,
+            rotation_system,
+            frames,
+        })
+    }
+
+    pub fn load<S: AsRef<str>>(file_name: S) -> Result<Self, String> {
+        let bytes = std::fs::read(file_name.as_ref())
+            .map_err(|e| format!("failed to read {}: {}", file_name.as_ref(), e))?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn save<S: AsRef<str>>(&self, file_name: S) -> Result<(), String> {
+        std::fs::write(file_name.as_ref(), self.to_bytes())
+            .map_err(|e| format!("failed to write {}: {}", file_name.as_ref(), e))
+    }
+}
+
+// LEB128: 7 bits per byte with the high bit set on every byte but the last
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if self.pos + count > self.bytes.len() {
+            return Err("replay file is truncated".to_string());
+        }
+
+        let bytes = &self.bytes[self.pos..self.pos + count];
+        self.pos += count;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_varint(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err("replay file has a malformed number".to_string())
+    }
+}
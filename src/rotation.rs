@@ -0,0 +1,372 @@
+use crate::block::*;
+use crate::util::*;
+use std::fmt::Debug;
+
+macro_rules! cell_array {
+    ( $(($x:expr,$y:expr)),* $(,)?) => {
+        [
+            $(
+                Vec2{x: $x, y: $y},
+            )*
+        ]
+    };
+}
+
+macro_rules! kick_list {
+    ( $(($x:expr,$y:expr)),* $(,)?) => {
+        vec![
+            $(
+                Vec2{x: $x, y: $y},
+            )*
+        ]
+    };
+}
+
+/// The rules for how blocks are laid out and turned: which way each block spawns, where its cells
+/// are in each orientation and which offsets to try when a rotation is blocked. Systems are
+/// stateless so blocks can share one through a `&'static` reference.
+pub trait RotationSystem: Debug + Sync {
+    /// The orientation a new or held block of this type comes out in
+    fn spawn_rotation(&self, block_type: BlockType) -> Rotation;
+
+    /// The positions of a block's 4 cells relative to the top-left of its bounding box
+    fn cells(&self, block_type: BlockType, rot: Rotation) -> [Vec2; 4];
+
+    /// The offsets to try, in order, when turning `block` to `dest_rot`. `is_occupied` says
+    /// whether a cell, relative to the block's current position, is blocked, for systems whose
+    /// kicks depend on what's in the way.
+    fn kicks(
+        &self,
+        block: Block,
+        dest_rot: Rotation,
+        is_occupied: &dyn Fn(Vec2) -> bool,
+    ) -> Vec<Vec2>;
+
+    /// Whether a T block turned into place with the kick at `kick_index` always makes a full
+    /// T-spin, even when it would otherwise only be a mini
+    fn is_full_t_spin_kick(&self, kick_index: usize) -> bool;
+}
+
+/// The guideline Super Rotation System
+#[derive(Debug)]
+pub struct SuperRotationSystem;
+
+/// The Arika rotation system from the TGM games. Blocks sit flat side down in their box, I, S and
+/// Z only have two orientations and blocked rotations try one cell right and then one cell left.
+#[derive(Debug)]
+pub struct ArikaRotationSystem;
+
+/// An NES-style system: T, J and L spawn pointing down, I, S and Z only have two orientations and
+/// a blocked rotation simply fails
+#[derive(Debug)]
+pub struct ClassicRotationSystem;
+
+/// The built-in rotation systems, for picking one by name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RotationSystemType {
+    #[default]
+    Srs,
+    Ars,
+    Classic,
+}
+
+impl RotationSystemType {
+    pub const ALL: [RotationSystemType; 3] = [
+        RotationSystemType::Srs,
+        RotationSystemType::Ars,
+        RotationSystemType::Classic,
+    ];
+
+    pub fn system(&self) -> &'static dyn RotationSystem {
+        match *self {
+            RotationSystemType::Srs => &SRS,
+            RotationSystemType::Ars => &ARS,
+            RotationSystemType::Classic => &CLASSIC,
+        }
+    }
+
+    /// The name used for the system in config files
+    pub fn name(&self) -> &'static str {
+        match *self {
+            RotationSystemType::Srs => "srs",
+            RotationSystemType::Ars => "ars",
+            RotationSystemType::Classic => "classic",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|system_type| system_type.name() == name)
+            .copied()
+    }
+}
+
+pub static SRS: SuperRotationSystem = SuperRotationSystem;
+pub static ARS: ArikaRotationSystem = ArikaRotationSystem;
+pub static CLASSIC: ClassicRotationSystem = ClassicRotationSystem;
+
+impl RotationSystem for SuperRotationSystem {
+    fn spawn_rotation(&self, _block_type: BlockType) -> Rotation {
+        Rotation::Rot0
+    }
+
+    fn cells(&self, block_type: BlockType, rot: Rotation) -> [Vec2; 4] {
+        match block_type {
+            // - - - -    - - 0 -    - - - -    - 0 - -
+            // 0 1 2 3 => - - 1 - => - - - - => - 1 - -
+            // - - - -    - - 2 -    0 1 2 3    - 2 - -
+            // - - - -    - - 3 -    - - - -    - 3 - -
+            BlockType::I => match rot {
+                Rotation::Rot0 => cell_array![(0, 1), (1, 1), (2, 1), (3, 1),],
+                Rotation::Rot1 => cell_array![(2, 0), (2, 1), (2, 2), (2, 3),],
+                Rotation::Rot2 => cell_array![(0, 2), (1, 2), (2, 2), (3, 2),],
+                Rotation::Rot3 => cell_array![(1, 0), (1, 1), (1, 2), (1, 3),],
+            },
+
+            // - 0 1 -    - 0 1 -    - 0 1 -    - 0 1 -
+            // - 2 3 - => - 2 3 - => - 2 3 - => - 2 3 -
+            // - - - -    - - - -    - - - -    - - - -
+            // - - - -    - - - -    - - - -    - - - -
+            BlockType::O => cell_array![(1, 0), (2, 0), (1, 1), (2, 1),],
+
+            // - 0 -    - 0 -    - - -    - 0 -
+            // 1 2 3 => - 1 2 => 0 1 2 => 1 2 -
+            // - - -    - 3 -    - 3 -    - 3 -
+            BlockType::T => match rot {
+                Rotation::Rot0 => cell_array![(1, 0), (0, 1), (1, 1), (2, 1),],
+                Rotation::Rot1 => cell_array![(1, 0), (1, 1), (2, 1), (1, 2),],
+                Rotation::Rot2 => cell_array![(0, 1), (1, 1), (2, 1), (1, 2),],
+                Rotation::Rot3 => cell_array![(1, 0), (0, 1), (1, 1), (1, 2),],
+            },
+
+            // - 0 1    - 0 -    - - -    0 - -
+            // 2 3 - => - 1 2 => - 0 1 => 1 2 -
+            // - - -    - - 3    2 3 -    - 3 -
+            BlockType::S => match rot {
+                Rotation::Rot0 => cell_array![(1, 0), (2, 0), (0, 1), (1, 1),],
+                Rotation::Rot1 => cell_array![(1, 0), (1, 1), (2, 1), (2, 2),],
+                Rotation::Rot2 => cell_array![(1, 1), (2, 1), (0, 2), (1, 2),],
+                Rotation::Rot3 => cell_array![(0, 0), (0, 1), (1, 1), (1, 2),],
+            },
+
+            // 0 1 -    - - 0    - - -    - 0 -
+            // - 2 3 => - 1 2 => 0 1 - => 1 2 -
+            // - - -    - 3 -    - 2 3    3 - -
+            BlockType::Z => match rot {
+                Rotation::Rot0 => cell_array![(0, 0), (1, 0), (1, 1), (2, 1),],
+                Rotation::Rot1 => cell_array![(2, 0), (1, 1), (2, 1), (1, 2),],
+                Rotation::Rot2 => cell_array![(0, 1), (1, 1), (1, 2), (2, 2),],
+                Rotation::Rot3 => cell_array![(1, 0), (0, 1), (1, 1), (0, 2),],
+            },
+
+            // 0 - -    - 0 1    - - -    - 0 -
+            // 1 2 3 => - 2 - => 0 1 2 => - 1 -
+            // - - -    - 3 -    - - 3    2 3 -
+            BlockType::J => match rot {
+                Rotation::Rot0 => cell_array![(0, 0), (0, 1), (1, 1), (2, 1),],
+                Rotation::Rot1 => cell_array![(1, 0), (2, 0), (1, 1), (1, 2),],
+                Rotation::Rot2 => cell_array![(0, 1), (1, 1), (2, 1), (2, 2),],
+                Rotation::Rot3 => cell_array![(1, 0), (1, 1), (0, 2), (1, 2),],
+            },
+
+            // - - 0    - 0 -    - - -    0 1 -
+            // 1 2 3 => - 1 - => 0 1 2 => - 2 -
+            // - - -    - 2 3    3 - -    - 3 -
+            BlockType::L => match rot {
+                Rotation::Rot0 => cell_array![(2, 0), (0, 1), (1, 1), (2, 1),],
+                Rotation::Rot1 => cell_array![(1, 0), (1, 1), (1, 2), (2, 2),],
+                Rotation::Rot2 => cell_array![(0, 1), (1, 1), (2, 1), (0, 2),],
+                Rotation::Rot3 => cell_array![(0, 0), (1, 0), (1, 1), (1, 2),],
+            },
+        }
+    }
+
+    // The guideline tables have y pointing up, so every y here is flipped from the usual charts.
+    // Half turns use the SRS+ table from TETR.IO, which isn't the same as two quarter turns.
+    fn kicks(
+        &self,
+        block: Block,
+        dest_rot: Rotation,
+        _is_occupied: &dyn Fn(Vec2) -> bool,
+    ) -> Vec<Vec2> {
+        match block.block_type {
+            // O blocks don't change shape so they never need kicking
+            BlockType::O => kick_list![(0, 0)],
+            _ if block.rot.rotate(2) == Ok(dest_rot) => match block.rot {
+                Rotation::Rot0 => kick_list![(0, 0), (0, -1), (1, -1), (-1, -1), (1, 0), (-1, 0)],
+                Rotation::Rot1 => kick_list![(0, 0), (1, 0), (1, -2), (1, -1), (0, -2), (0, -1)],
+                Rotation::Rot2 => kick_list![(0, 0), (0, 1), (-1, 1), (1, 1), (-1, 0), (1, 0)],
+                Rotation::Rot3 => kick_list![(0, 0), (-1, 0), (-1, -2), (-1, -1), (0, -2), (0, -1)],
+            },
+            BlockType::I => match (block.rot, dest_rot) {
+                (Rotation::Rot0, Rotation::Rot1) | (Rotation::Rot3, Rotation::Rot2) => {
+                    kick_list![(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)]
+                }
+                (Rotation::Rot1, Rotation::Rot0) | (Rotation::Rot2, Rotation::Rot3) => {
+                    kick_list![(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)]
+                }
+                (Rotation::Rot1, Rotation::Rot2) | (Rotation::Rot0, Rotation::Rot3) => {
+                    kick_list![(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)]
+                }
+                (Rotation::Rot2, Rotation::Rot1) | (Rotation::Rot3, Rotation::Rot0) => {
+                    kick_list![(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)]
+                }
+                (r1, r2) => panic!("{:?} >> {:?} is an invalid rotation", r1, r2),
+            },
+            _ => match (block.rot, dest_rot) {
+                (Rotation::Rot0, Rotation::Rot1) | (Rotation::Rot2, Rotation::Rot1) => {
+                    kick_list![(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]
+                }
+                (Rotation::Rot1, Rotation::Rot0) | (Rotation::Rot1, Rotation::Rot2) => {
+                    kick_list![(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)]
+                }
+                (Rotation::Rot2, Rotation::Rot3) | (Rotation::Rot0, Rotation::Rot3) => {
+                    kick_list![(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)]
+                }
+                (Rotation::Rot3, Rotation::Rot2) | (Rotation::Rot3, Rotation::Rot0) => {
+                    kick_list![(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]
+                }
+                (r1, r2) => panic!("{:?} >> {:?} is an invalid rotation", r1, r2),
+            },
+        }
+    }
+
+    // The last quarter turn kick is the one which lifts a T up into a T-spin triple slot
+    fn is_full_t_spin_kick(&self, kick_index: usize) -> bool {
+        kick_index == 4
+    }
+}
+
+impl RotationSystem for ArikaRotationSystem {
+    fn spawn_rotation(&self, _block_type: BlockType) -> Rotation {
+        Rotation::Rot0
+    }
+
+    fn cells(&self, block_type: BlockType, rot: Rotation) -> [Vec2; 4] {
+        match block_type {
+            // - - - -    - - 0 -
+            // 0 1 2 3 => - - 1 -
+            // - - - -    - - 2 -
+            // - - - -    - - 3 -
+            BlockType::I => match rot {
+                Rotation::Rot0 | Rotation::Rot2 => cell_array![(0, 1), (1, 1), (2, 1), (3, 1),],
+                Rotation::Rot1 | Rotation::Rot3 => cell_array![(2, 0), (2, 1), (2, 2), (2, 3),],
+            },
+
+            // - - - -
+            // - 0 1 -
+            // - 2 3 -
+            // - - - -
+            BlockType::O => cell_array![(1, 1), (2, 1), (1, 2), (2, 2),],
+
+            // - - -    - 0 -    - - -    - 0 -
+            // 0 1 2 => 1 2 - => - 0 - => - 1 2
+            // - 3 -    - 3 -    1 2 3    - 3 -
+            BlockType::T => match rot {
+                Rotation::Rot0 => cell_array![(0, 1), (1, 1), (2, 1), (1, 2),],
+                Rotation::Rot1 => cell_array![(1, 0), (0, 1), (1, 1), (1, 2),],
+                Rotation::Rot2 => cell_array![(1, 1), (0, 2), (1, 2), (2, 2),],
+                Rotation::Rot3 => cell_array![(1, 0), (1, 1), (2, 1), (1, 2),],
+            },
+
+            // - - -    0 - -
+            // - 0 1 => 1 2 -
+            // 2 3 -    - 3 -
+            BlockType::S => match rot {
+                Rotation::Rot0 | Rotation::Rot2 => cell_array![(1, 1), (2, 1), (0, 2), (1, 2),],
+                Rotation::Rot1 | Rotation::Rot3 => cell_array![(0, 0), (0, 1), (1, 1), (1, 2),],
+            },
+
+            // - - -    - - 0
+            // 0 1 - => - 1 2
+            // - 2 3    - 3 -
+            BlockType::Z => match rot {
+                Rotation::Rot0 | Rotation::Rot2 => cell_array![(0, 1), (1, 1), (1, 2), (2, 2),],
+                Rotation::Rot1 | Rotation::Rot3 => cell_array![(2, 0), (1, 1), (2, 1), (1, 2),],
+            },
+
+            // - - -    - 0 -    - - -    - 0 1
+            // 0 1 2 => - 1 - => 0 - - => - 2 -
+            // - - 3    2 3 -    1 2 3    - 3 -
+            BlockType::J => match rot {
+                Rotation::Rot0 => cell_array![(0, 1), (1, 1), (2, 1), (2, 2),],
+                Rotation::Rot1 => cell_array![(1, 0), (1, 1), (0, 2), (1, 2),],
+                Rotation::Rot2 => cell_array![(0, 1), (0, 2), (1, 2), (2, 2),],
+                Rotation::Rot3 => cell_array![(1, 0), (2, 0), (1, 1), (1, 2),],
+            },
+
+            // - - -    0 1 -    - - -    - 0 -
+            // 0 1 2 => - 2 - => - - 0 => - 1 -
+            // 3 - -    - 3 -    1 2 3    - 2 3
+            BlockType::L => match rot {
+                Rotation::Rot0 => cell_array![(0, 1), (1, 1), (2, 1), (0, 2),],
+                Rotation::Rot1 => cell_array![(0, 0), (1, 0), (1, 1), (1, 2),],
+                Rotation::Rot2 => cell_array![(2, 1), (0, 2), (1, 2), (2, 2),],
+                Rotation::Rot3 => cell_array![(1, 0), (1, 1), (1, 2), (2, 2),],
+            },
+        }
+    }
+
+    // L, J and T follow the center column rule: reading the turned block's cells left to right
+    // and top to bottom, if the first blocked cell is in the middle column the block can't kick
+    fn kicks(
+        &self,
+        block: Block,
+        dest_rot: Rotation,
+        is_occupied: &dyn Fn(Vec2) -> bool,
+    ) -> Vec<Vec2> {
+        match block.block_type {
+            BlockType::I | BlockType::O => kick_list![(0, 0)],
+            BlockType::L | BlockType::J | BlockType::T => {
+                let mut rotated_cells = self.cells(block.block_type, dest_rot);
+                rotated_cells.sort_by_key(|cell| (cell.y, cell.x));
+                let first_blocked_cell = rotated_cells.iter().find(|cell| is_occupied(**cell));
+                match first_blocked_cell {
+                    Some(cell) if cell.x == 1 => kick_list![(0, 0)],
+                    _ => kick_list![(0, 0), (1, 0), (-1, 0)],
+                }
+            }
+            BlockType::S | BlockType::Z => kick_list![(0, 0), (1, 0), (-1, 0)],
+        }
+    }
+
+    fn is_full_t_spin_kick(&self, _kick_index: usize) -> bool {
+        false
+    }
+}
+
+impl RotationSystem for ClassicRotationSystem {
+    fn spawn_rotation(&self, block_type: BlockType) -> Rotation {
+        match block_type {
+            BlockType::T | BlockType::J | BlockType::L => Rotation::Rot2,
+            BlockType::I | BlockType::O | BlockType::S | BlockType::Z => Rotation::Rot0,
+        }
+    }
+
+    // The SRS layouts, except that I, S and Z flip between their lower flat state and one
+    // upright state instead of walking around their box
+    fn cells(&self, block_type: BlockType, rot: Rotation) -> [Vec2; 4] {
+        let rot = match block_type {
+            BlockType::I | BlockType::S | BlockType::Z => match rot {
+                Rotation::Rot0 | Rotation::Rot2 => Rotation::Rot2,
+                Rotation::Rot1 | Rotation::Rot3 => Rotation::Rot1,
+            },
+            BlockType::O | BlockType::T | BlockType::J | BlockType::L => rot,
+        };
+        SRS.cells(block_type, rot)
+    }
+
+    fn kicks(
+        &self,
+        _block: Block,
+        _dest_rot: Rotation,
+        _is_occupied: &dyn Fn(Vec2) -> bool,
+    ) -> Vec<Vec2> {
+        kick_list![(0, 0)]
+    }
+
+    fn is_full_t_spin_kick(&self, _kick_index: usize) -> bool {
+        false
+    }
+}
@@ -0,0 +1,780 @@
+use crate::block::*;
+use crate::game::*;
+use crate::keybindings::*;
+use crate::leaderboard::*;
+use crate::pacing::*;
+use crate::render::*;
+use crate::util::*;
+use std::time::Duration;
+
+pub const TITLE: &str = "TETRUST";
+
+/// The smallest screen, in characters, which every screen fits on. The game screen is the widest
+/// once the longest line clear banner is showing.
+pub const MIN_SCREEN_WIDTH: i32 = 66;
+pub const MIN_SCREEN_HEIGHT: i32 = 24;
+
+const TITLE_LINES: [&str; 7] = [
+    r#" _____________"#,
+    r#"/\____________\ ___  _____  ___  .   .   ___   _____"#,
+    r#"\/___/\   \___/ \___    \   \ _)  \   \  \ ___    \"#,
+    r#"     \ \   \     \___    \   \  \  \___\   ___\    \"#,
+    r#"      \ \   \"#,
+    r#"       \ \___\"#,
+    r#"        \/___/"#,
+];
+
+/// Where each part of the game screen is drawn
+pub struct GameLayout {
+    pub board_rect: Rect,
+    pub board_frame_rect: Rect,
+    pub title_rect: Rect,
+    pub preview_frame_rect: Rect,
+    pub preview_rect: Rect,
+    pub hold_frame_rect: Rect,
+    pub hold_rect: Rect,
+    pub score_frame_rect: Rect,
+    // rows from the top of one preview block to the next
+    preview_slot_height: i32,
+}
+
+impl GameLayout {
+    const BOARD_WIDTH: i32 = GameStateBuilder::STANDARD_BOARD_WIDTH;
+    const BOARD_HEIGHT: i32 = GameStateBuilder::STANDARD_BOARD_HEIGHT;
+
+    // Every preview block is rendered in a slot 2 rows tall with a row of padding between slots,
+    // unless the queue is too long to fit beside the board that way
+    const PREVIEW_SLOT_HEIGHT: i32 = 3;
+    const PACKED_PREVIEW_SLOT_HEIGHT: i32 = 2;
+    const PREVIEW_WIDTH: i32 = 4;
+
+    /// Lays the game screen out in the middle of a screen of the given size, with room to preview
+    /// a next queue of the given length
+    pub fn new(window_height: i32, window_width: i32, next_queue_len: usize) -> Self {
+        let board_rect = Rect {
+            left: (window_width / 2) - Self::BOARD_WIDTH - 2, // arrange the board on the left side of the middle of the screen
+            top: (window_height - Self::BOARD_HEIGHT) / 2,    // center the board within the window
+            width: Self::BOARD_WIDTH,
+            height: Self::BOARD_HEIGHT,
+        };
+
+        let board_frame_rect = Rect {
+            left: board_rect.left - 1,
+            top: board_rect.top - 1,
+            width: board_rect.width + 2,
+            height: board_rect.height + 2,
+        };
+
+        let title_rect = Rect {
+            left: board_frame_rect.right() + 2,
+            top: board_frame_rect.top,
+            width: (TITLE.len() + 4) as i32,
+            height: 3,
+        };
+
+        // Blocks are 2 rows tall so the last slot doesn't need its padding. The seed goes two rows
+        // below the queue, which should still be beside the board.
+        let preview_frame_top = title_rect.bottom() + 2;
+        let preview_frame_height = |slot_height: i32| {
+            let padding = slot_height - Self::PACKED_PREVIEW_SLOT_HEIGHT;
+            (next_queue_len as i32) * slot_height - padding + 2
+        };
+        let padded_seed_row =
+            preview_frame_top + preview_frame_height(Self::PREVIEW_SLOT_HEIGHT) + 1;
+        let preview_slot_height = if padded_seed_row <= board_frame_rect.bottom() {
+            Self::PREVIEW_SLOT_HEIGHT
+        } else {
+            Self::PACKED_PREVIEW_SLOT_HEIGHT
+        };
+
+        let preview_frame_rect = Rect {
+            left: title_rect.left,
+            top: preview_frame_top,
+            width: Self::PREVIEW_WIDTH + 2,
+            height: preview_frame_height(preview_slot_height),
+        };
+
+        let preview_rect = Rect {
+            left: preview_frame_rect.left + 1,
+            top: preview_frame_rect.top + 1,
+            width: preview_frame_rect.width - 2,
+            height: preview_frame_rect.height - 2,
+        };
+
+        let hold_frame_rect = Rect {
+            left: board_frame_rect.left - 2 - preview_frame_rect.width,
+            top: preview_frame_rect.top,
+            width: preview_frame_rect.width,
+            height: Self::PREVIEW_SLOT_HEIGHT + 1,
+        };
+
+        let hold_rect = Rect {
+            left: hold_frame_rect.left + 1,
+            top: hold_frame_rect.top + 1,
+            width: hold_frame_rect.width - 2,
+            height: hold_frame_rect.height - 2,
+        };
+
+        // the score pane sits below the hold piece so the preview queue has room to grow
+        let score_frame_rect = {
+            const SCORE_FRAME_WIDTH: i32 = 17;
+            Rect {
+                left: board_frame_rect.left - 2 - SCORE_FRAME_WIDTH,
+                top: hold_frame_rect.bottom() + 2,
+                width: SCORE_FRAME_WIDTH,
+                height: 7,
+            }
+        };
+
+        GameLayout {
+            board_rect,
+            board_frame_rect,
+            title_rect,
+            preview_frame_rect,
+            preview_rect,
+            hold_frame_rect,
+            hold_rect,
+            score_frame_rect,
+            preview_slot_height,
+        }
+    }
+}
+
+/// A leaderboard entry whose initials are still being typed in
+pub struct PendingEntry<'a> {
+    /// the entry's index on the leaderboard
+    pub place: usize,
+    pub initials: &'a str,
+    pub score: usize,
+    pub seed: u64,
+}
+
+pub fn mode_name(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Marathon => "Marathon",
+        GameMode::Sprint { .. } => "Sprint",
+        GameMode::Ultra { .. } => "Ultra",
+    }
+}
+
+pub fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{:02}:{:02}.{:03}",
+        millis / 60_000,
+        (millis / 1000) % 60,
+        millis % 1000
+    )
+}
+
+/// Marathon and ultra leaderboards hold scores and sprint leaderboards hold times in milliseconds
+pub fn format_leaderboard_score(mode: GameMode, score: usize) -> String {
+    match mode {
+        GameMode::Marathon | GameMode::Ultra { .. } => format!("{:05}", score),
+        GameMode::Sprint { .. } => format_time(Duration::from_millis(score as u64)),
+    }
+}
+
+pub fn line_clear_label(line_clear: &LineClear) -> String {
+    let lines_label = match line_clear.lines {
+        0 => "",
+        1 => " SINGLE",
+        2 => " DOUBLE",
+        3 => " TRIPLE",
+        _ => " TETRIS",
+    };
+
+    let t_spin_label = match line_clear.t_spin {
+        TSpin::None => "",
+        TSpin::Mini => "T-SPIN MINI",
+        TSpin::Full => "T-SPIN",
+    };
+
+    format!("{}{}", t_spin_label, lines_label)
+        .trim()
+        .to_string()
+}
+
+/// Whether the screen is too small for the screens to fit on. Each screen draws a message asking
+/// for a bigger screen instead of itself until it's big enough.
+pub fn is_screen_too_small(renderer: &dyn Renderer) -> bool {
+    let (window_height, window_width) = renderer.size();
+    window_height < MIN_SCREEN_HEIGHT || window_width < MIN_SCREEN_WIDTH
+}
+
+fn render_screen_too_small(renderer: &mut dyn Renderer) {
+    let (window_height, window_width) = renderer.size();
+    renderer.clear();
+    renderer.draw_text_centered(
+        "Please enlarge the terminal",
+        window_width / 2,
+        window_height / 2 - 1,
+        Style::PLAIN,
+    );
+    renderer.draw_text_centered(
+        &format!(
+            "{}x{} is smaller than {}x{}",
+            window_width, window_height, MIN_SCREEN_WIDTH, MIN_SCREEN_HEIGHT
+        ),
+        window_width / 2,
+        window_height / 2,
+        Style::PLAIN,
+    );
+}
+
+fn block_style(block_type: BlockType) -> Style {
+    Style::color(Color::Block(block_type))
+}
+
+fn render_cell(
+    renderer: &mut dyn Renderer,
+    cell_rel_pos: Vec2,
+    rel_pos_offset_x: i32,
+    rel_pos_offset_y: i32,
+    block_type: BlockType,
+) {
+    renderer.draw_char(
+        cell_rel_pos.x + rel_pos_offset_x,
+        cell_rel_pos.y + rel_pos_offset_y,
+        block_type.sprite_char(),
+        block_style(block_type),
+    );
+}
+
+fn render_block(
+    renderer: &mut dyn Renderer,
+    block_rel_pos: Vec2,
+    rel_pos_offset_x: i32,
+    rel_pos_offset_y: i32,
+    block: Block,
+    style: Style,
+) {
+    for cell_pos in &block.cells() {
+        // Ok to blit block sprite even if position is OOB
+        renderer.draw_char(
+            cell_pos.x + block_rel_pos.x + rel_pos_offset_x,
+            cell_pos.y + block_rel_pos.y + rel_pos_offset_y,
+            block.sprite_char(),
+            style,
+        );
+    }
+}
+
+/// Draws the title card above a list of options with the cursor next to the selected one
+pub fn render_title_menu(renderer: &mut dyn Renderer, menu_options: &[&str], menu_cursor: usize) {
+    if is_screen_too_small(renderer) {
+        render_screen_too_small(renderer);
+        return;
+    }
+
+    let (window_height, window_width) = renderer.size();
+
+    let title_rect = {
+        let title_width = TITLE_LINES.iter().map(|line| line.len()).max().unwrap() as i32;
+        const TITLE_HEIGHT: i32 = TITLE_LINES.len() as i32;
+
+        Rect {
+            // center the title horizontally
+            left: (window_width - title_width) / 2,
+            // place the title just above the horizontal divide
+            top: (window_height / 2) - (TITLE_HEIGHT + 1),
+            width: title_width,
+            height: TITLE_HEIGHT,
+        }
+    };
+
+    let menu_rect = {
+        let menu_width = menu_options
+            .iter()
+            .map(|option_text| option_text.len())
+            .max()
+            .unwrap() as i32;
+        let menu_height = menu_options.len() as i32;
+
+        Rect {
+            // center the menu options horizontally
+            left: (window_width - menu_width) / 2,
+            // place the menu options just below the horizontal divide
+            top: (window_height / 2) + 1,
+            // Add 2 characters to the menu width to account for the cursor
+            width: menu_width + 2,
+            height: menu_height,
+        }
+    };
+
+    renderer.clear();
+
+    // Render the title card
+    for (i, title_line) in TITLE_LINES.iter().enumerate() {
+        let row_offset = (i as i32) + title_rect.top;
+        renderer.draw_text(
+            title_rect.left,
+            row_offset,
+            title_line,
+            Style::color(Color::Menu(i)),
+        );
+    }
+
+    // Render the menu options
+    for (i, menu_line) in menu_options.iter().enumerate() {
+        let row_offset = (i as i32) + menu_rect.top;
+        if i == menu_cursor {
+            renderer.draw_text(menu_rect.left, row_offset, "> ", Style::PLAIN);
+        }
+        renderer.draw_text(menu_rect.left + 2, row_offset, menu_line, Style::PLAIN);
+    }
+}
+
+/// Draws the whole game screen. `line_clear_banner` is the name of a recent line clear to flash
+/// below the score pane.
+pub fn render_game(
+    renderer: &mut dyn Renderer,
+    layout: &GameLayout,
+    game_state: &GameState,
+    seed: u64,
+    line_clear_banner: Option<&str>,
+    is_paused: bool,
+) {
+    if is_screen_too_small(renderer) {
+        render_screen_too_small(renderer);
+        return;
+    }
+
+    let GameLayout {
+        board_rect,
+        board_frame_rect,
+        title_rect,
+        preview_frame_rect,
+        preview_rect,
+        hold_frame_rect,
+        hold_rect,
+        score_frame_rect,
+        preview_slot_height,
+    } = layout;
+
+    renderer.clear();
+
+    // Render the tetris title
+    renderer.draw_frame(title_rect);
+    renderer.draw_text_centered(
+        TITLE,
+        title_rect.center_x(),
+        title_rect.center_y(),
+        Style::PLAIN,
+    );
+
+    // Render next piece preview
+    renderer.draw_text_centered(
+        "Next",
+        preview_frame_rect.center_x(),
+        preview_frame_rect.top - 1,
+        Style::PLAIN,
+    );
+    renderer.draw_frame(preview_frame_rect);
+    for (i, preview_block) in game_state
+        .preview_blocks(game_state.next_queue_len())
+        .iter()
+        .enumerate()
+    {
+        // NOTE: the top row of the I block's spawn orientation is empty, so shift it up to
+        // keep it inside its slot
+        let preview_block_pos = Vec2 {
+            x: 0,
+            y: (i as i32) * preview_slot_height - preview_block.top(),
+        };
+        render_block(
+            renderer,
+            preview_block_pos,
+            preview_rect.left,
+            preview_rect.top,
+            *preview_block,
+            block_style(preview_block.block_type),
+        );
+    }
+
+    // Render the hold piece
+    renderer.draw_text_centered(
+        "Hold",
+        hold_frame_rect.center_x(),
+        hold_frame_rect.top - 1,
+        Style::PLAIN,
+    );
+    renderer.draw_frame(hold_frame_rect);
+    if let Some(hold_block) = game_state.hold_block() {
+        // dim the held piece until it can be swapped back in
+        let mut style = block_style(hold_block.block_type);
+        if !game_state.can_hold() {
+            style = style.dim();
+        }
+        render_block(
+            renderer,
+            Vec2 {
+                x: 0,
+                y: -hold_block.top(),
+            },
+            hold_rect.left,
+            hold_rect.top,
+            hold_block,
+            style,
+        );
+    }
+
+    // Show the seed so the run can be shared and replayed
+    renderer.draw_text(
+        preview_frame_rect.left,
+        preview_frame_rect.bottom() + 2,
+        &format!("Seed: {}", seed),
+        Style::PLAIN,
+    );
+
+    // Render the score pane. Sprints are raced against the clock so they show the time, the
+    // latest split and how many lines have been cleared instead of the score. Ultras count down
+    // to the end of the game.
+    let mut stats = match game_state.mode() {
+        GameMode::Marathon => vec![
+            format!("Level: {:05}", game_state.level()),
+            format!("Score: {:05}", game_state.score()),
+        ],
+        GameMode::Sprint { line_goal } => {
+            let splits = game_state.splits();
+            let latest_split = match splits.last() {
+                Some(split) => format!(
+                    "{}L: {}",
+                    splits.len() * GameMode::SPRINT_SPLIT_INTERVAL,
+                    format_time(*split)
+                ),
+                None => String::new(),
+            };
+            vec![
+                format!("Time: {}", format_time(game_state.elapsed_time())),
+                latest_split,
+                format!("Lines: {:02}/{:02}", game_state.lines_cleared(), line_goal),
+            ]
+        }
+        GameMode::Ultra { .. } => vec![
+            format!(
+                "Time: {}",
+                format_time(game_state.remaining_time().unwrap())
+            ),
+            format!("Score: {:05}", game_state.score()),
+        ],
+    };
+    stats.push(format!("Combo: {:05}", game_state.combo()));
+    if game_state.back_to_back() {
+        stats.push("Back-to-back".to_string());
+    }
+    for (i, stat) in stats.iter().enumerate() {
+        renderer.draw_text_centered(
+            stat,
+            score_frame_rect.center_x(),
+            score_frame_rect.top + 1 + i as i32,
+            Style::PLAIN,
+        );
+    }
+    renderer.draw_frame(score_frame_rect);
+
+    // Flash the name of the last line clear below the score pane
+    if let Some(label) = line_clear_banner {
+        renderer.draw_text_centered(
+            label,
+            score_frame_rect.center_x(),
+            score_frame_rect.bottom() + 2,
+            Style::PLAIN.blink(),
+        );
+    }
+
+    // Render the board frame
+    renderer.draw_frame(board_frame_rect);
+
+    // Render the active piece
+    if let Some((block, block_pos)) = game_state.active_block() {
+        // TOOD: mayhaps refactor this into its own helper?
+        // render the active piece's drop trail
+        for cell in &block.cells() {
+            let start_row = cell.y + block_pos.y;
+            let col = cell.x + block_pos.x;
+            for row in start_row..board_rect.height {
+                renderer.draw_char(
+                    col + board_rect.left,
+                    row + board_rect.top,
+                    '-',
+                    Style::PLAIN,
+                );
+            }
+        }
+
+        render_block(
+            renderer,
+            block_pos,
+            board_rect.left,
+            board_rect.top,
+            block,
+            block_style(block.block_type),
+        );
+    }
+
+    // Render the settled pieces. Rows being cleared flash and dissolve from the middle outwards
+    // over the line clear delay.
+    let clearing_rows = game_state.clearing_rows();
+    let board_center = game_state.width() as f32 / 2.0;
+    let dissolved_half_width = game_state.line_clear_progress() * board_center;
+    game_state.for_each_settled_piece(|block_type: BlockType, cell_pos: Vec2| {
+        if clearing_rows.contains(&cell_pos.y) {
+            let distance_from_center = (cell_pos.x as f32 + 0.5 - board_center).abs();
+            if distance_from_center < dissolved_half_width {
+                return;
+            }
+
+            renderer.draw_char(
+                cell_pos.x + board_rect.left,
+                cell_pos.y + board_rect.top,
+                block_type.sprite_char(),
+                block_style(block_type).blink(),
+            );
+        } else {
+            render_cell(
+                renderer,
+                cell_pos,
+                board_rect.left,
+                board_rect.top,
+                block_type,
+            );
+        }
+    });
+
+    // If the game is over, render the game over text
+    if let Some(game_over_reason) = game_state.game_over_reason() {
+        let game_over_text = match game_over_reason {
+            GameOverReason::ToppedOut => "Game Over",
+            GameOverReason::GoalComplete => "Finished",
+            GameOverReason::TimeUp => "Time's Up",
+        };
+
+        renderer.draw_text_centered(
+            game_over_text,
+            board_rect.center_x(),
+            board_rect.center_y(),
+            Style::PLAIN.blink(),
+        );
+    }
+    // If the game is paused, render pause text
+    else if is_paused {
+        renderer.draw_text_centered(
+            "PAUSE",
+            board_rect.center_x(),
+            board_rect.center_y(),
+            Style::PLAIN.blink(),
+        );
+    }
+}
+
+/// Draws a replay's playback state and controls below the board of a game screen
+pub fn render_replay_status(renderer: &mut dyn Renderer, layout: &GameLayout, status: &str) {
+    if is_screen_too_small(renderer) {
+        return;
+    }
+
+    // one line so it still fits below the board on the smallest screen
+    renderer.draw_text_centered(
+        &format!("{}  P pause  1/2/4 speed  . step", status),
+        layout.board_frame_rect.center_x(),
+        layout.board_frame_rect.bottom() + 1,
+        Style::PLAIN,
+    );
+}
+
+/// Shows the frame rate and the average time spent running each frame in the top left corner
+pub fn render_frame_stats(renderer: &mut dyn Renderer, frame_stats: &FrameStats) {
+    renderer.draw_text(
+        0,
+        0,
+        &format!(
+            "FPS: {:3}  Frame: {:.1}ms",
+            frame_stats.fps(),
+            frame_stats.average_work_time().as_secs_f32() * 1000.0
+        ),
+        Style::PLAIN,
+    );
+}
+
+/// Draws a mode's leaderboard. A pending entry is drawn blinking in its place with the entries
+/// below it moved down a row.
+pub fn render_leaderboard(
+    renderer: &mut dyn Renderer,
+    mode: GameMode,
+    leaderboard: &Leaderboard,
+    pending_entry: Option<&PendingEntry>,
+) {
+    if is_screen_too_small(renderer) {
+        render_screen_too_small(renderer);
+        return;
+    }
+
+    let leaderboard_rect = {
+        //              Marathon Leaderboard
+        //
+        // #00    FML    00000    seed 00000000000000000000
+        //
+        // #01    FML    00000    seed 00000000000000000000
+        // ...
+        // #10    FML    00000    seed 00000000000000000000
+        const LEADERBOARD_ENTRY_WIDTH: i32 = 52;
+        const LEADERBOARD_HEIGHT: i32 = (2 * (Leaderboard::max_entries() + 1) - 1) as i32;
+
+        let (window_height, window_width) = renderer.size();
+        Rect {
+            left: (window_width - LEADERBOARD_ENTRY_WIDTH) / 2,
+            top: (window_height - LEADERBOARD_HEIGHT) / 2,
+            width: LEADERBOARD_ENTRY_WIDTH,
+            height: LEADERBOARD_HEIGHT,
+        }
+    };
+
+    let leaderboard_frame_rect = Rect {
+        left: leaderboard_rect.left - 1,
+        top: leaderboard_rect.top - 1,
+        width: leaderboard_rect.width + 2,
+        height: leaderboard_rect.height + 2,
+    };
+
+    renderer.clear();
+    renderer.draw_frame(&leaderboard_frame_rect);
+    renderer.draw_text_centered(
+        &format!("{} Leaderboard", mode_name(mode)),
+        leaderboard_rect.center_x(),
+        leaderboard_rect.top,
+        Style::PLAIN,
+    );
+
+    let format_entry = |leaderboard_pos: usize, name: &str, score: usize, seed: String| {
+        format!(
+            "#{:02}    {:3}    {}    seed {:>20}",
+            leaderboard_pos,
+            name,
+            format_leaderboard_score(mode, score),
+            seed
+        )
+    };
+
+    let skip_entry = pending_entry.map(|entry| entry.place);
+    for i in 0..Leaderboard::max_entries() {
+        let entry = leaderboard.entry(i);
+        let (name, score, seed) = entry
+            .map(|e| (e.name.as_ref(), e.score, e.seed.to_string()))
+            .unwrap_or(("---", 0, String::from("---")));
+
+        let mut leaderboard_pos = i + 1;
+        if skip_entry.is_some() && skip_entry.unwrap() <= i {
+            leaderboard_pos += 1;
+        }
+
+        if leaderboard_pos <= Leaderboard::max_entries() {
+            let row_offset = (leaderboard_pos * 2) as i32;
+
+            renderer.draw_text_centered(
+                &format_entry(leaderboard_pos, name, score, seed),
+                leaderboard_rect.center_x(),
+                leaderboard_rect.top + row_offset,
+                Style::PLAIN,
+            );
+        }
+    }
+
+    // Render the new entry WIP space
+    if let Some(entry) = pending_entry {
+        let leaderboard_pos = entry.place + 1;
+        let row_offset = (leaderboard_pos * 2) as i32;
+        renderer.draw_text_centered(
+            &format_entry(
+                leaderboard_pos,
+                entry.initials,
+                entry.score,
+                entry.seed.to_string(),
+            ),
+            leaderboard_rect.center_x(),
+            leaderboard_rect.top + row_offset,
+            Style::PLAIN.blink(),
+        );
+    }
+}
+
+/// Lists every control and its keys above the options to reset them or leave. `menu_cursor`
+/// counts the controls first, in the order of [`Control::ALL`], then the two options. The list
+/// scrolls to keep the cursor on screen when it doesn't all fit.
+pub fn render_settings(
+    renderer: &mut dyn Renderer,
+    key_bindings: &KeyBindings,
+    menu_cursor: usize,
+    message: &str,
+) {
+    if is_screen_too_small(renderer) {
+        render_screen_too_small(renderer);
+        return;
+    }
+
+    const OPTIONS: [&str; 2] = ["Reset to defaults", "Back"];
+    const LABEL_WIDTH: usize = 20;
+    const SETTINGS_WIDTH: i32 = 50;
+
+    let option_count = Control::ALL.len() + OPTIONS.len();
+    let (window_height, window_width) = renderer.size();
+    // the title and the message each take a row and a gap
+    let visible_rows = std::cmp::min(option_count, window_height as usize - 4);
+    let first_row = menu_cursor.saturating_sub(visible_rows - 1);
+    let settings_rect = Rect {
+        left: (window_width - SETTINGS_WIDTH) / 2,
+        top: (window_height - (visible_rows as i32 + 4)) / 2,
+        width: SETTINGS_WIDTH,
+        height: visible_rows as i32 + 4,
+    };
+
+    renderer.clear();
+    renderer.draw_text_centered(
+        "Settings",
+        settings_rect.center_x(),
+        settings_rect.top,
+        Style::PLAIN,
+    );
+
+    let rows = Control::ALL
+        .iter()
+        .map(|control| {
+            let keys = key_bindings
+                .keys(*control)
+                .iter()
+                .map(Key::name)
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("{:width$}{}", control.label(), keys, width = LABEL_WIDTH)
+        })
+        .chain(OPTIONS.iter().map(|option| option.to_string()));
+    for (i, row) in rows.skip(first_row).take(visible_rows).enumerate() {
+        renderer.draw_text(
+            settings_rect.left + 2,
+            settings_rect.top + 2 + i as i32,
+            &row,
+            Style::PLAIN,
+        );
+    }
+
+    renderer.draw_text(
+        settings_rect.left,
+        settings_rect.top + 2 + (menu_cursor - first_row) as i32,
+        "> ",
+        Style::PLAIN,
+    );
+
+    renderer.draw_text_centered(
+        message,
+        settings_rect.center_x(),
+        settings_rect.bottom(),
+        Style::PLAIN,
+    );
+}
+
+/// Clears the screen and shows a message in the middle of it
+pub fn render_message(renderer: &mut dyn Renderer, message: &str) {
+    let (window_height, window_width) = renderer.size();
+    renderer.clear();
+    renderer.draw_text_centered(message, window_width / 2, window_height / 2, Style::PLAIN);
+}
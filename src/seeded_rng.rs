@@ -0,0 +1,41 @@
+use snm_rand_utils::range_rng::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small deterministic rng (splitmix64) so the same seed always deals the same pieces
+pub struct SeededRangeRng {
+    state: u64,
+}
+
+impl SeededRangeRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRangeRng { state: seed }
+    }
+
+    /// The seed shared by every game played on the same (UTC) day as `now`
+    pub fn daily_seed(now: SystemTime) -> u64 {
+        const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+        let day = now
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs() / SECONDS_PER_DAY)
+            .unwrap_or(0);
+
+        // scramble the day so neighbouring days don't get neighbouring seeds
+        SeededRangeRng::new(day).next_u64()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+impl RangeRng<usize> for SeededRangeRng {
+    // Generates a value in the range [lower, upper)
+    fn gen_range(&mut self, lower: usize, upper: usize) -> usize {
+        assert!(lower < upper);
+        lower + (self.next_u64() % ((upper - lower) as u64)) as usize
+    }
+}
@@ -0,0 +1,135 @@
+use crate::action::*;
+use crate::game::*;
+use std::time::Duration;
+
+/// A list of actions, each tagged with how far into the game (in milliseconds) it happens. Every
+/// line of a script file holds one action:
+///
+/// ```text
+/// # comments and blank lines are skipped
+/// 0 left
+/// 120 rotate_right
+/// 500 hard_drop
+/// ```
+#[derive(Debug, PartialEq, Eq)]
+pub struct InputScript {
+    actions: Vec<(Duration, Action)>,
+}
+
+impl InputScript {
+    pub fn parse<S: AsRef<str>>(script: S) -> Result<Self, String> {
+        let mut actions = vec![];
+        for (line_index, line) in script.as_ref().lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let line_number = line_index + 1;
+            let mut tokens = line.split_whitespace();
+            let (timestamp, action) = match (tokens.next(), tokens.next(), tokens.next()) {
+                (Some(timestamp), Some(action), None) => (timestamp, action),
+                _ => {
+                    return Err(format!(
+                        "line {}: expected '<milliseconds> <action>'",
+                        line_number
+                    ))
+                }
+            };
+
+            let timestamp = timestamp.parse::<u64>().map_err(|e| {
+                format!(
+                    "line {}: bad timestamp '{}' ({})",
+                    line_number, timestamp, e
+                )
+            })?;
+            let action = Action::from_name(action)
+                .ok_or_else(|| format!("line {}: unknown action '{}'", line_number, action))?;
+
+            actions.push((Duration::from_millis(timestamp), action));
+        }
+
+        // the script file doesn't have to be written in order
+        actions.sort_by_key(|(timestamp, _)| *timestamp);
+        Ok(InputScript { actions })
+    }
+
+    pub fn load<S: AsRef<str>>(file_name: S) -> Result<Self, String> {
+        let script = std::fs::read_to_string(file_name.as_ref())
+            .map_err(|e| format!("failed to read {}: {}", file_name.as_ref(), e))?;
+        Self::parse(script)
+    }
+
+    fn end_time(&self) -> Duration {
+        self.actions
+            .last()
+            .map(|(timestamp, _)| *timestamp)
+            .unwrap_or_default()
+    }
+}
+
+pub struct SimulationResult {
+    pub score: usize,
+    pub lines: usize,
+    pub level: usize,
+    pub game_over_reason: Option<GameOverReason>,
+    pub board: String,
+}
+
+/// Plays a full game without a terminal. The game runs until it ends or until the last scripted
+/// action has been played, whichever comes first.
+pub fn run_simulation(seed: u64, mode: GameMode, script: &InputScript) -> SimulationResult {
+    const SIMULATION_STEP: Duration = Duration::from_millis(10);
+
+    // plays on the same board and rules as the terminal game
+    let mut game_state = GameStateBuilder::standard(mode, seed).build();
+
+    let mut game_time = Duration::from_millis(0);
+    let mut next_action = 0;
+    while !game_state.is_game_over() && game_time <= script.end_time() {
+        while let Some((timestamp, action)) = script.actions.get(next_action) {
+            if *timestamp > game_time {
+                break;
+            }
+            action.apply(&mut game_state);
+            next_action += 1;
+        }
+
+        game_state.update(SIMULATION_STEP);
+        game_time += SIMULATION_STEP;
+    }
+
+    SimulationResult {
+        score: game_state.score(),
+        lines: game_state.lines_cleared(),
+        level: game_state.level(),
+        game_over_reason: game_state.game_over_reason(),
+        board: render_board_text(&game_state),
+    }
+}
+
+/// Draws the board one row per line with '.' for empty cells
+pub fn render_board_text(game_state: &GameState) -> String {
+    let mut board = vec![vec!['.'; game_state.width() as usize]; game_state.height() as usize];
+
+    game_state.for_each_settled_piece(|block_type, pos| {
+        board[pos.y as usize][pos.x as usize] = block_type.sprite_char();
+    });
+
+    if let Some((block, block_pos)) = game_state.active_block() {
+        for cell in &block.cells() {
+            let x = cell.x + block_pos.x;
+            let y = cell.y + block_pos.y;
+            // the active block may still be partly above the board
+            if y >= 0 {
+                board[y as usize][x as usize] = block.sprite_char();
+            }
+        }
+    }
+
+    board
+        .iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
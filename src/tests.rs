@@ -1,9 +1,21 @@
-// TODO: need to add tests for rotation kicks
 #[cfg(test)]
 mod tests {
+    use crate::action::*;
     use crate::block::*;
+    use crate::config::*;
     use crate::game::*;
+    use crate::handling::*;
+    use crate::input::*;
+    use crate::keybindings::*;
     use crate::leaderboard::*;
+    use crate::pacing::*;
+    use crate::piece_generator::*;
+    use crate::render::*;
+    use crate::replay::*;
+    use crate::rotation::*;
+    use crate::screens::*;
+    use crate::seeded_rng::*;
+    use crate::simulator::*;
     use crate::util::*;
     use snm_rand_utils::range_rng::*;
     use snm_rand_utils::*;
@@ -31,6 +43,27 @@ mod tests {
         }
     }
 
+    impl GameState {
+        // Shorthand for setting up a game in progress with all of the usual test settings
+        fn make_from_seed(
+            board: &[Vec<bool>],
+            active_block: Block,
+            active_block_pos: Vec2,
+            score: usize,
+            line_score: usize,
+            lock_delay: LockDelay,
+            piece_generator: Box<dyn PieceGenerator>,
+        ) -> Self {
+            GameStateBuilder::from_board(board)
+                .active_block(active_block, active_block_pos)
+                .score(score)
+                .lines_cleared(line_score)
+                .lock_delay(lock_delay)
+                .piece_generator(piece_generator)
+                .build()
+        }
+    }
+
     fn tick(game_state: &mut GameState) {
         const STEADY_TICK: std::time::Duration = std::time::Duration::from_millis(250);
         game_state.update(STEADY_TICK);
@@ -39,7 +72,11 @@ mod tests {
     fn default_test_board(block_type_rng: Box<dyn RangeRng<usize>>) -> GameState {
         const TEST_BOARD_WIDTH: i32 = 20;
         const TEST_BOARD_HEIGHT: i32 = 30;
-        GameState::new(TEST_BOARD_WIDTH, TEST_BOARD_HEIGHT, block_type_rng)
+        GameStateBuilder::new(TEST_BOARD_WIDTH, TEST_BOARD_HEIGHT)
+            .next_queue_len(1)
+            .lock_delay(LockDelay::instant())
+            .piece_generator(Box::new(MemorylessGenerator::new(block_type_rng)))
+            .build()
     }
 
     fn test_board_from_seed(
@@ -55,7 +92,8 @@ mod tests {
             active_block_pos,
             score,
             line_score,
-            Box::new(ThreadRangeRng::new()),
+            LockDelay::instant(),
+            Box::new(MemorylessGenerator::new(Box::new(ThreadRangeRng::new()))),
         )
     }
 
@@ -157,15 +195,21 @@ mod tests {
 
         // This test generates only 'O' pieces perfectly stacking on each on the board and
         // verifies the end state
-        let mut game_state = GameState::new(
-            TEST_BOARD_WIDTH,
-            TEST_BOARD_HEIGHT,
-            Box::new(mocks::SingleValueRangeRng::new(BlockType::O as usize)),
-        );
+        let mut game_state = GameStateBuilder::new(TEST_BOARD_WIDTH, TEST_BOARD_HEIGHT)
+            .next_queue_len(1)
+            .lock_delay(LockDelay::instant())
+            .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::O as usize),
+            ))))
+            .build();
 
         while !game_state.is_game_over() {
             tick(&mut game_state);
         }
+        assert_eq!(
+            game_state.game_over_reason(),
+            Some(GameOverReason::ToppedOut)
+        );
 
         /*
         // each 'O' piece will start horizontal and they all will perfectly stack
@@ -210,6 +254,106 @@ mod tests {
         assert_eq!(active_block_pos, Vec2 { x: 0, y: -2 });
     }
 
+    fn take_pieces(piece_generator: &mut dyn PieceGenerator, count: usize) -> Vec<BlockType> {
+        (0..count).map(|_| piece_generator.next_piece()).collect()
+    }
+
+    #[test]
+    fn test_memoryless_generator() {
+        let mut piece_generator =
+            MemorylessGenerator::new(Box::new(mocks::SequenceRangeRng::new(&[
+                BlockType::T as usize,
+                BlockType::T as usize,
+                BlockType::I as usize,
+            ])));
+
+        assert_eq!(
+            take_pieces(&mut piece_generator, 6),
+            vec![
+                BlockType::T,
+                BlockType::T,
+                BlockType::I,
+                BlockType::T,
+                BlockType::T,
+                BlockType::I
+            ]
+        );
+    }
+
+    #[test]
+    fn test_7_bag_generator() {
+        // always pulling the first piece out of the bag deals the bag back in order
+        let mut piece_generator =
+            BagGenerator::new(1, Box::new(mocks::SingleValueRangeRng::new(1)));
+        let expected_bag = BLOCKTYPES.to_vec();
+        assert_eq!(take_pieces(&mut piece_generator, 7), expected_bag);
+        assert_eq!(take_pieces(&mut piece_generator, 7), expected_bag);
+
+        // pulling from the middle of the bag
+        let mut piece_generator = BagGenerator::new(
+            1,
+            Box::new(mocks::SequenceRangeRng::new(&[3, 3, 3, 3, 3, 2, 1])),
+        );
+        assert_eq!(
+            take_pieces(&mut piece_generator, 7),
+            vec![
+                BlockType::T,
+                BlockType::S,
+                BlockType::Z,
+                BlockType::J,
+                BlockType::L,
+                BlockType::O,
+                BlockType::I
+            ]
+        );
+    }
+
+    #[test]
+    fn test_7_bag_generator_deals_every_piece_per_bag() {
+        let mut piece_generator = PieceGeneratorType::Bag7.create(Box::new(ThreadRangeRng::new()));
+        for _ in 0..10 {
+            let mut bag = take_pieces(piece_generator.as_mut(), BLOCKTYPES.len());
+            bag.sort_by_key(|block_type| *block_type as usize);
+            assert_eq!(bag, BLOCKTYPES.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_14_bag_generator() {
+        let mut piece_generator =
+            BagGenerator::new(2, Box::new(mocks::SingleValueRangeRng::new(1)));
+        let expected_bag: Vec<BlockType> = BLOCKTYPES.iter().chain(&BLOCKTYPES).copied().collect();
+        assert_eq!(take_pieces(&mut piece_generator, 14), expected_bag);
+
+        let mut piece_generator = PieceGeneratorType::Bag14.create(Box::new(ThreadRangeRng::new()));
+        for _ in 0..10 {
+            let bag = take_pieces(piece_generator.as_mut(), 2 * BLOCKTYPES.len());
+            for block_type in BLOCKTYPES.iter() {
+                assert_eq!(bag.iter().filter(|b| *b == block_type).count(), 2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_history_generator() {
+        // The first piece is never an S, Z or O. Rolls are taken from the 4 allowed first pieces.
+        let mut piece_generator = HistoryGenerator::new(Box::new(mocks::SequenceRangeRng::new(&[
+            2, // T from the first piece choices
+            3, // T, rerolled since T was just dealt
+            2, // O
+            1, // I
+            3, // T, rerolled since T is in the history
+            3, // T, rerolled again
+            3, // T, rerolled again
+            3, // T, kept since we've run out of rerolls
+        ])));
+
+        assert_eq!(
+            take_pieces(&mut piece_generator, 4),
+            vec![BlockType::T, BlockType::O, BlockType::I, BlockType::T]
+        );
+    }
+
     fn active_block_distance_to_left_wall(game_state: &GameState) -> i32 {
         let block = game_state.active_block().unwrap();
         let active_block_pos = block.1;
@@ -313,7 +457,8 @@ mod tests {
         //     oo  $$
         //      xx$$
         //     xx
-        for _ in 0..(game_state.height() - 1) {
+        // It has already fallen a row in the tick it spawned in.
+        for _ in 0..(game_state.height() - 2) {
             tick(&mut game_state);
         }
 
@@ -396,7 +541,141 @@ mod tests {
     }
 
     #[test]
-    fn test_score_1_line() {
+    fn test_preview_queue() {
+        let sequence = [
+            BlockType::I,
+            BlockType::O,
+            BlockType::T,
+            BlockType::S,
+            BlockType::Z,
+            BlockType::J,
+            BlockType::L,
+        ];
+
+        const NEXT_QUEUE_LEN: usize = 4;
+        let mut game_state = GameStateBuilder::new(10, 40)
+            .next_queue_len(NEXT_QUEUE_LEN)
+            .lock_delay(LockDelay::instant())
+            .piece_generator(Box::new(BagGenerator::new(
+                1,
+                Box::new(mocks::SingleValueRangeRng::new(1)),
+            )))
+            .build();
+        assert_eq!(game_state.next_queue_len(), NEXT_QUEUE_LEN);
+
+        // asking for more blocks than are queued only returns the queued blocks
+        assert_eq!(game_state.preview_blocks(10).len(), NEXT_QUEUE_LEN);
+
+        for piece_index in 0..(sequence.len() * 2) {
+            // the queue always holds the next N blocks in order
+            let preview_types: Vec<BlockType> = game_state
+                .preview_blocks(NEXT_QUEUE_LEN)
+                .iter()
+                .map(|block| block.block_type)
+                .collect();
+            let expected_types: Vec<BlockType> = (0..NEXT_QUEUE_LEN)
+                .map(|i| sequence[(piece_index + i) % sequence.len()])
+                .collect();
+            assert_eq!(preview_types, expected_types);
+            assert_eq!(game_state.preview_blocks(2).len(), 2);
+            assert_eq!(game_state.preview_block().block_type, expected_types[0]);
+
+            // pull the front of the queue into play, which dropping the last block does straight
+            // away with no entry delay
+            if piece_index == 0 {
+                tick(&mut game_state);
+            } else {
+                drop_active_block(&mut game_state);
+            }
+            assert_eq!(
+                game_state.active_block().unwrap().0.block_type,
+                expected_types[0]
+            );
+            assert_eq!(game_state.next_queue_len(), NEXT_QUEUE_LEN);
+        }
+    }
+
+    fn lock_delay_test_board(lock_delay: LockDelay) -> GameState {
+        let mut game_state = GameStateBuilder::new(20, 30)
+            .next_queue_len(1)
+            .lock_delay(lock_delay)
+            .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::T as usize),
+            ))))
+            .build();
+
+        // generate the first block and rest it on the floor
+        tick(&mut game_state);
+        game_state.quick_drop();
+        game_state
+    }
+
+    #[test]
+    fn test_lock_delay_expires() {
+        let mut game_state = lock_delay_test_board(LockDelay::guideline());
+
+        game_state.update(std::time::Duration::from_millis(499));
+        assert!(game_state.active_block().is_some());
+        assert_eq!(game_state.get_settled_piece_count(), 0);
+
+        // with no entry delay the next block spawns as soon as the last one locks
+        game_state.update(std::time::Duration::from_millis(1));
+        assert_eq!(game_state.get_settled_piece_count(), 4);
+        assert!(game_state.active_block().unwrap().1.y < 0);
+    }
+
+    #[test]
+    fn test_lock_delay_move_reset() {
+        let mut game_state = lock_delay_test_board(LockDelay::guideline());
+
+        // moving the block restarts the lock delay
+        game_state.update(std::time::Duration::from_millis(400));
+        game_state.move_active_block_horizontal(-1);
+        game_state.update(std::time::Duration::from_millis(400));
+        assert_eq!(game_state.get_settled_piece_count(), 0);
+
+        game_state.update(std::time::Duration::from_millis(100));
+        assert_eq!(game_state.get_settled_piece_count(), 4);
+    }
+
+    #[test]
+    fn test_lock_delay_reset_limit() {
+        let mut game_state = lock_delay_test_board(LockDelay::guideline());
+
+        // shuffle the block back and forth along the floor using up all but one of its resets
+        let mut horizontal_motion = 1;
+        for _ in 0..14 {
+            game_state.move_active_block_horizontal(horizontal_motion);
+            game_state.update(std::time::Duration::from_millis(100));
+            assert_eq!(game_state.get_settled_piece_count(), 0);
+            horizontal_motion = -horizontal_motion;
+        }
+
+        // the last reset is spent and the block locks immediately rather than waiting out the delay
+        game_state.move_active_block_horizontal(horizontal_motion);
+        game_state.update(std::time::Duration::from_millis(1));
+        assert_eq!(game_state.get_settled_piece_count(), 4);
+    }
+
+    #[test]
+    fn test_infinite_lock_delay() {
+        let mut game_state = lock_delay_test_board(LockDelay::infinite());
+
+        let mut horizontal_motion = 1;
+        for _ in 0..100 {
+            game_state.move_active_block_horizontal(horizontal_motion);
+            game_state.update(std::time::Duration::from_millis(400));
+            assert_eq!(game_state.get_settled_piece_count(), 0);
+            horizontal_motion = -horizontal_motion;
+        }
+
+        // leaving the block alone still locks it
+        game_state.update(std::time::Duration::from_millis(500));
+        assert_eq!(game_state.get_settled_piece_count(), 4);
+    }
+
+    #[test]
+    fn test_hard_drop() {
         let board = [
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
@@ -406,26 +685,150 @@ mod tests {
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
-            vec![true, false, true, true, true, true],
+            vec![true, false, false, false, false, false],
         ];
 
+        // a flat T block dropped from row 0 comes to rest on the floor 7 rows below
         let active_block = Block {
-            rot: Rotation::Rot2,
-            block_type: BlockType::T,
+            rot: Rotation::Rot0,
+            ..Block::new(BlockType::T)
         };
-        let active_block_pos = Vec2::zero();
+        let active_block_pos = Vec2 { x: 2, y: 0 };
 
-        let start_score = 120;
-        let mut game_state =
-            test_board_from_seed(&board, active_block, active_block_pos, start_score, 3);
-        assert_eq!(game_state.score(), start_score);
+        let mut game_state = GameState::make_from_seed(
+            &board,
+            active_block,
+            active_block_pos,
+            0,
+            0,
+            LockDelay::guideline(),
+            Box::new(MemorylessGenerator::new(Box::new(ThreadRangeRng::new()))),
+        );
+
+        // hard dropping locks the block without waiting out the lock delay and awards 2 points per
+        // cell dropped
+        game_state.hard_drop();
+        assert!(game_state.active_block().is_none());
+        assert_eq!(game_state.get_settled_piece_count(), 1 + 4);
+        assert_eq!(game_state.score(), 2 * 7);
+    }
+
+    #[test]
+    fn test_soft_drop() {
+        let mut game_state = default_test_board(Box::new(mocks::SingleValueRangeRng::new(
+            BlockType::O as usize,
+        )));
+        tick(&mut game_state);
+        let (_, start_pos) = game_state.active_block().unwrap();
+
+        // a regular tick of gravity moves the block down one cell and awards no points
+        tick(&mut game_state);
+        assert_eq!(game_state.active_block().unwrap().1.y, start_pos.y + 1);
+        assert_eq!(game_state.score(), 0);
+
+        // soft dropping speeds gravity up and awards 1 point per cell
+        game_state.set_soft_drop(true);
+        tick(&mut game_state);
+        let cells_soft_dropped = game_state.active_block().unwrap().1.y - (start_pos.y + 1);
+        assert!(cells_soft_dropped > 1);
+        assert_eq!(game_state.score(), cells_soft_dropped as usize);
+
+        // releasing soft drop restores regular gravity
+        game_state.set_soft_drop(false);
+        tick(&mut game_state);
+        assert_eq!(
+            game_state.active_block().unwrap().1.y,
+            start_pos.y + 1 + cells_soft_dropped + 1
+        );
+        assert_eq!(game_state.score(), cells_soft_dropped as usize);
+    }
+
+    #[test]
+    fn test_hold_once_per_drop() {
+        let mut game_state = default_test_board(Box::new(mocks::SequenceRangeRng::new(&[
+            BlockType::T as usize,
+            BlockType::S as usize,
+            BlockType::L as usize,
+        ])));
+
+        // generate the first block
+        tick(&mut game_state);
+        assert_eq!(
+            game_state.active_block().unwrap().0.block_type,
+            BlockType::T
+        );
+        assert!(game_state.hold_block().is_none());
+        assert!(game_state.can_hold());
+
+        // holding with an empty hold slot pulls in the next block
+        game_state.rotate_block(1).unwrap();
+        game_state.hold();
+        let (active_block, active_block_pos) = game_state.active_block().unwrap();
+        assert_eq!(active_block.block_type, BlockType::S);
+        assert!(active_block_pos.y < 0);
+        assert_eq!(game_state.preview_block().block_type, BlockType::L);
+
+        // the held block is stored in its spawn orientation
+        let hold_block = game_state.hold_block().unwrap();
+        assert_eq!(hold_block.block_type, BlockType::T);
+        assert!(matches!(hold_block.rot, Rotation::Rot0));
+
+        // a second hold before the active block locks is ignored
+        assert!(!game_state.can_hold());
+        game_state.hold();
+        assert_eq!(
+            game_state.active_block().unwrap().0.block_type,
+            BlockType::S
+        );
+        assert_eq!(game_state.hold_block().unwrap().block_type, BlockType::T);
 
+        // once the block locks, holding swaps the held block back in
         drop_active_block(&mut game_state);
-        assert_eq!(game_state.score(), start_score + 40);
+        tick(&mut game_state);
+        assert!(game_state.can_hold());
+        assert_eq!(
+            game_state.active_block().unwrap().0.block_type,
+            BlockType::L
+        );
+        game_state.hold();
+        assert_eq!(
+            game_state.active_block().unwrap().0.block_type,
+            BlockType::T
+        );
+        assert_eq!(game_state.hold_block().unwrap().block_type, BlockType::L);
+        assert!(!game_state.can_hold());
     }
 
     #[test]
-    fn test_score_2_line() {
+    fn test_hold_during_game_over() {
+        let mut game_state = default_test_board(Box::new(mocks::SequenceRangeRng::new(&[
+            BlockType::O as usize,
+            BlockType::I as usize,
+        ])));
+
+        while !game_state.is_game_over() {
+            tick(&mut game_state);
+        }
+
+        let (active_block, active_block_pos) = game_state.active_block().unwrap();
+        let hold_block = game_state.hold_block();
+        let preview_block = game_state.preview_block();
+        game_state.hold();
+
+        let (final_active_block, final_active_block_pos) = game_state.active_block().unwrap();
+        assert_eq!(final_active_block.block_type, active_block.block_type);
+        assert_eq!(final_active_block_pos, active_block_pos);
+        assert!(game_state.hold_block().is_none());
+        assert!(hold_block.is_none());
+        assert_eq!(
+            game_state.preview_block().block_type,
+            preview_block.block_type
+        );
+        assert!(game_state.is_game_over());
+    }
+
+    #[test]
+    fn test_score_1_line() {
         let board = [
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
@@ -434,13 +837,13 @@ mod tests {
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
-            vec![true, false, true, true, true, true],
+            vec![false, false, false, false, false, false],
             vec![true, false, true, true, true, true],
         ];
 
         let active_block = Block {
-            rot: Rotation::Rot3,
-            block_type: BlockType::L,
+            rot: Rotation::Rot2,
+            ..Block::new(BlockType::T)
         };
         let active_block_pos = Vec2::zero();
 
@@ -454,7 +857,7 @@ mod tests {
     }
 
     #[test]
-    fn test_score_3_line() {
+    fn test_score_2_line() {
         let board = [
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
@@ -462,14 +865,14 @@ mod tests {
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
-            vec![true, false, true, true, true, true],
+            vec![false, false, false, false, false, false],
             vec![true, false, true, true, true, true],
             vec![true, false, true, true, true, true],
         ];
 
         let active_block = Block {
             rot: Rotation::Rot3,
-            block_type: BlockType::I,
+            ..Block::new(BlockType::L)
         };
         let active_block_pos = Vec2::zero();
 
@@ -483,14 +886,14 @@ mod tests {
     }
 
     #[test]
-    fn test_score_4_line() {
+    fn test_score_3_line() {
         let board = [
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
-            vec![true, false, true, true, true, true],
+            vec![false, false, false, false, false, false],
             vec![true, false, true, true, true, true],
             vec![true, false, true, true, true, true],
             vec![true, false, true, true, true, true],
@@ -498,7 +901,7 @@ mod tests {
 
         let active_block = Block {
             rot: Rotation::Rot3,
-            block_type: BlockType::I,
+            ..Block::new(BlockType::I)
         };
         let active_block_pos = Vec2::zero();
 
@@ -508,17 +911,18 @@ mod tests {
         assert_eq!(game_state.score(), start_score);
 
         drop_active_block(&mut game_state);
-        assert_eq!(game_state.score(), start_score + 1200);
+        assert_eq!(game_state.score(), start_score + 500);
     }
 
     #[test]
-    fn test_level_calculation_1() {
+    fn test_score_4_line() {
+        // the lone cell above the stack keeps the tetris from also being a perfect clear
         let board = [
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
-            vec![false, false, false, false, false, false],
+            vec![false, false, false, false, false, true],
             vec![true, false, true, true, true, true],
             vec![true, false, true, true, true, true],
             vec![true, false, true, true, true, true],
@@ -527,133 +931,2679 @@ mod tests {
 
         let active_block = Block {
             rot: Rotation::Rot3,
-            block_type: BlockType::I,
+            ..Block::new(BlockType::I)
         };
         let active_block_pos = Vec2::zero();
 
-        let start_score = 160;
+        let start_score = 120;
         let mut game_state =
-            test_board_from_seed(&board, active_block, active_block_pos, start_score, 4);
-
-        let start_level = 1;
+            test_board_from_seed(&board, active_block, active_block_pos, start_score, 3);
         assert_eq!(game_state.score(), start_score);
-        assert_eq!(game_state.level(), start_level);
 
         drop_active_block(&mut game_state);
-        assert!(game_state.score() > start_score);
-        assert_eq!(game_state.level(), start_level + 1);
+        assert_eq!(game_state.score(), start_score + 800);
     }
 
     #[test]
-    fn test_level_calculation_2() {
+    fn test_score_scales_with_level() {
         let board = [
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
             vec![false, false, false, false, false, false],
-            vec![true, false, true, true, true, true],
-            vec![true, false, true, true, true, true],
-            vec![true, false, true, true, true, true],
+            vec![false, false, false, false, false, false],
+            vec![false, false, false, false, false, false],
+            vec![false, false, false, false, false, false],
             vec![true, false, true, true, true, true],
         ];
 
         let active_block = Block {
-            rot: Rotation::Rot3,
-            block_type: BlockType::I,
+            rot: Rotation::Rot2,
+            ..Block::new(BlockType::T)
         };
         let active_block_pos = Vec2::zero();
 
-        let start_score = 2400 + 40; // 9 lines = 2 tetrises + a single line clear
+        let start_score = 120;
         let mut game_state =
-            test_board_from_seed(&board, active_block, active_block_pos, start_score, 9);
-
-        let start_level = 2;
-        assert_eq!(game_state.score(), start_score);
-        assert_eq!(game_state.level(), start_level);
+            test_board_from_seed(&board, active_block, active_block_pos, start_score, 12);
+        assert_eq!(game_state.level(), 3);
 
         drop_active_block(&mut game_state);
-        assert!(game_state.score() > start_score);
-        assert_eq!(game_state.level(), start_level + 1);
+        assert_eq!(game_state.score(), start_score + 100 * 3);
+        assert_eq!(
+            game_state.take_events(),
+            vec![GameEvent::LineClear(LineClear {
+                lines: 1,
+                t_spin: TSpin::None,
+                back_to_back: false,
+                combo: 1,
+                score: 300,
+            })]
+        );
     }
 
     #[test]
-    fn test_leaderboard() {
-        let mut leaderboard = Leaderboard::new();
+    fn test_t_spin_double() {
+        let board = [
+            vec![false, false, false, false, false, false, false, false],
+            vec![false, false, false, false, false, false, false, false],
+            vec![false, false, true, false, false, false, false, false],
+            vec![true, true, false, false, false, true, true, true],
+            vec![true, true, true, false, true, true, true, true],
+            vec![true, true, true, true, true, true, true, false],
+        ];
 
-        struct TestData {
-            score: usize,
-            expected_pos: usize,
-        }
+        // spin a T block pointing right into the slot under the overhang so it points down
+        let active_block = Block {
+            rot: Rotation::Rot1,
+            ..Block::new(BlockType::T)
+        };
+        let active_block_pos = Vec2 { x: 2, y: 2 };
 
-        let test_values = [
-            TestData {
-                score: 700,
-                expected_pos: 0,
-            },
-            TestData {
-                score: 800,
-                expected_pos: 0,
-            },
-            TestData {
-                score: 750,
-                expected_pos: 1,
-            },
-            TestData {
-                score: 500,
-                expected_pos: 3,
-            },
-            TestData {
-                score: 200,
-                expected_pos: 4,
-            },
-            TestData {
-                score: 250,
-                expected_pos: 4,
-            },
-            TestData {
-                score: 300,
-                expected_pos: 4,
-            },
-            TestData {
-                score: 775,
-                expected_pos: 1,
-            },
-            TestData {
-                score: 100,
-                expected_pos: 8,
-            },
-            TestData {
-                score: 50,
-                expected_pos: 9,
-            },
-            TestData {
-                score: 200,
-                expected_pos: 8,
-            },
+        let start_score = 120;
+        let mut game_state =
+            test_board_from_seed(&board, active_block, active_block_pos, start_score, 0);
+        game_state.rotate_block(1).unwrap();
+        let (rotated_block, rotated_block_pos) = game_state.active_block().unwrap();
+        assert!(matches!(rotated_block.rot, Rotation::Rot2));
+        assert_eq!(rotated_block_pos, active_block_pos);
+
+        game_state.hard_drop();
+        assert_eq!(game_state.score(), start_score + 1200);
+        assert_eq!(
+            game_state.take_events(),
+            vec![GameEvent::LineClear(LineClear {
+                lines: 2,
+                t_spin: TSpin::Full,
+                back_to_back: false,
+                combo: 1,
+                score: 1200,
+            })]
+        );
+
+        // events are only reported once
+        assert_eq!(game_state.take_events(), vec![]);
+    }
+
+    #[test]
+    fn test_t_spin_mini() {
+        let board = [
+            vec![false, false, false, false, false, false, false, false],
+            vec![false, false, false, false, false, false, false, false],
+            vec![false, false, false, false, true, false, false, false],
+            vec![false, false, false, false, false, false, false, false],
+            vec![true, true, true, false, true, true, true, true],
+            vec![true, true, true, true, true, true, true, false],
         ];
 
-        fn name_from_index(i: usize) -> String {
-            format!("n{:02}", i)
-        }
+        // spin a T block pointing down so it points left with only one of its front corners
+        // occupied
+        let active_block = Block {
+            rot: Rotation::Rot2,
+            ..Block::new(BlockType::T)
+        };
+        let active_block_pos = Vec2 { x: 2, y: 2 };
 
-        let final_score_indices: [usize; Leaderboard::max_entries()] =
-            [1, 7, 2, 0, 3, 6, 5, 4, 10, 8];
+        let mut game_state = test_board_from_seed(&board, active_block, active_block_pos, 0, 0);
+        game_state.rotate_block(1).unwrap();
+        let (rotated_block, rotated_block_pos) = game_state.active_block().unwrap();
+        assert!(matches!(rotated_block.rot, Rotation::Rot3));
+        assert_eq!(rotated_block_pos, active_block_pos);
+
+        game_state.hard_drop();
+        assert_eq!(game_state.score(), 200);
+        assert_eq!(
+            game_state.take_events(),
+            vec![GameEvent::LineClear(LineClear {
+                lines: 1,
+                t_spin: TSpin::Mini,
+                back_to_back: false,
+                combo: 1,
+                score: 200,
+            })]
+        );
+    }
+
+    #[test]
+    fn test_no_t_spin_without_rotation() {
+        let board = [
+            vec![false, false, false, false, false, false, false, false],
+            vec![false, false, false, false, false, false, false, false],
+            vec![false, false, false, false, false, false, false, false],
+            vec![true, true, false, false, false, true, true, true],
+            vec![true, true, true, false, true, true, true, true],
+            vec![true, true, true, true, true, true, true, false],
+        ];
+
+        // dropping a T block into a T-shaped slot without spinning it is just a double
+        let active_block = Block {
+            rot: Rotation::Rot2,
+            ..Block::new(BlockType::T)
+        };
+        let active_block_pos = Vec2 { x: 2, y: 0 };
+
+        let mut game_state = test_board_from_seed(&board, active_block, active_block_pos, 0, 0);
+        game_state.hard_drop();
+        assert_eq!(game_state.score(), 2 * 2 + 300);
+        assert_eq!(
+            game_state.take_events(),
+            vec![GameEvent::LineClear(LineClear {
+                lines: 2,
+                t_spin: TSpin::None,
+                back_to_back: false,
+                combo: 1,
+                score: 300,
+            })]
+        );
+    }
+
+    fn vec2s(points: &[(i32, i32)]) -> Vec<Vec2> {
+        points.iter().map(|(x, y)| Vec2 { x: *x, y: *y }).collect()
+    }
+
+    #[test]
+    fn test_rotation_system_layouts() {
+        let rotation_systems: [&'static dyn RotationSystem; 3] = [&SRS, &ARS, &CLASSIC];
+        let rotations = [
+            Rotation::Rot0,
+            Rotation::Rot1,
+            Rotation::Rot2,
+            Rotation::Rot3,
+        ];
+        for rotation_system in rotation_systems.iter() {
+            for block_type in BLOCKTYPES.iter() {
+                for rot in rotations.iter() {
+                    let cells = rotation_system.cells(*block_type, *rot);
+                    let message = format!("{:?} {:?} {:?}", rotation_system, block_type, rot);
+
+                    for (i, cell) in cells.iter().enumerate() {
+                        assert!(
+                            (0..4).contains(&cell.x) && (0..4).contains(&cell.y),
+                            "{}",
+                            message
+                        );
+                        assert!(!cells[..i].contains(cell), "{}", message);
+                    }
+
+                    // 4 cells are only connected if at least 3 pairs of them touch
+                    let touching_pairs = (0..4)
+                        .flat_map(|i| (i + 1..4).map(move |j| (i, j)))
+                        .filter(|(i, j)| {
+                            (cells[*i].x - cells[*j].x).abs() + (cells[*i].y - cells[*j].y).abs()
+                                == 1
+                        })
+                        .count();
+                    assert!(touching_pairs >= 3, "{}", message);
+                }
+
+                // four turns in either direction come back to the spawn layout
+                let block = Block::spawn(*block_type, *rotation_system);
+                for relative_rotation in [-1, 1].iter() {
+                    let turned_block = (0..4).fold(block, |turned_block, _| {
+                        turned_block.rotate(*relative_rotation).unwrap()
+                    });
+                    assert_eq!(turned_block.cells(), block.cells());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotation_system_spawn_rotations() {
+        #[rustfmt::skip]
+        let table: &[(&'static dyn RotationSystem, BlockType, Rotation)] = &[
+            (&SRS, BlockType::T, Rotation::Rot0),
+            (&SRS, BlockType::J, Rotation::Rot0),
+            (&SRS, BlockType::I, Rotation::Rot0),
+            (&ARS, BlockType::T, Rotation::Rot0),
+            (&ARS, BlockType::L, Rotation::Rot0),
+            (&ARS, BlockType::S, Rotation::Rot0),
+            (&CLASSIC, BlockType::T, Rotation::Rot2),
+            (&CLASSIC, BlockType::J, Rotation::Rot2),
+            (&CLASSIC, BlockType::L, Rotation::Rot2),
+            (&CLASSIC, BlockType::I, Rotation::Rot0),
+            (&CLASSIC, BlockType::Z, Rotation::Rot0),
+        ];
+
+        for (rotation_system, block_type, spawn_rotation) in table.iter() {
+            assert_eq!(
+                rotation_system.spawn_rotation(*block_type),
+                *spawn_rotation,
+                "{:?} {:?}",
+                rotation_system,
+                block_type
+            );
+        }
+
+        // ARS and classic T blocks both spawn pointing down, flat side up
+        let t_pointing_down = vec2s(&[(0, 1), (1, 1), (2, 1), (1, 2)]);
+        assert_eq!(
+            Block::spawn(BlockType::T, &ARS).cells().to_vec(),
+            t_pointing_down
+        );
+        assert_eq!(
+            Block::spawn(BlockType::T, &CLASSIC).cells().to_vec(),
+            t_pointing_down
+        );
+    }
+
+    #[test]
+    fn test_srs_kicks() {
+        // the block, the turn and the kicks it tries
+        type KickCase = (BlockType, Rotation, Rotation, [(i32, i32); 5]);
+
+        // the guideline tables with y flipped to point down
+        #[rustfmt::skip]
+        let table: &[KickCase] = &[
+            (BlockType::T, Rotation::Rot0, Rotation::Rot1, [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]),
+            (BlockType::T, Rotation::Rot1, Rotation::Rot0, [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)]),
+            (BlockType::T, Rotation::Rot1, Rotation::Rot2, [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)]),
+            (BlockType::T, Rotation::Rot2, Rotation::Rot1, [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]),
+            (BlockType::J, Rotation::Rot2, Rotation::Rot3, [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)]),
+            (BlockType::J, Rotation::Rot3, Rotation::Rot2, [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]),
+            (BlockType::L, Rotation::Rot3, Rotation::Rot0, [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]),
+            (BlockType::S, Rotation::Rot0, Rotation::Rot3, [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)]),
+            (BlockType::I, Rotation::Rot0, Rotation::Rot1, [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)]),
+            (BlockType::I, Rotation::Rot1, Rotation::Rot0, [(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)]),
+            (BlockType::I, Rotation::Rot1, Rotation::Rot2, [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)]),
+            (BlockType::I, Rotation::Rot2, Rotation::Rot1, [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)]),
+            (BlockType::I, Rotation::Rot2, Rotation::Rot3, [(0, 0), (2, 0), (-1, 0), (2, -1), (-1, 2)]),
+            (BlockType::I, Rotation::Rot3, Rotation::Rot2, [(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)]),
+            (BlockType::I, Rotation::Rot3, Rotation::Rot0, [(0, 0), (1, 0), (-2, 0), (1, 2), (-2, -1)]),
+            (BlockType::I, Rotation::Rot0, Rotation::Rot3, [(0, 0), (-1, 0), (2, 0), (-1, -2), (2, 1)]),
+        ];
+
+        for (block_type, rot, dest_rot, kicks) in table.iter() {
+            let block = Block {
+                rot: *rot,
+                ..Block::new(*block_type)
+            };
+            assert_eq!(
+                SRS.kicks(block, *dest_rot, &|_| false),
+                vec2s(kicks),
+                "{:?} {:?} >> {:?}",
+                block_type,
+                rot,
+                dest_rot
+            );
+        }
+
+        let o_block = Block::new(BlockType::O);
+        assert_eq!(
+            SRS.kicks(o_block, Rotation::Rot1, &|_| false),
+            vec2s(&[(0, 0)])
+        );
+    }
+
+    #[test]
+    fn test_ars_kicks() {
+        const NO_KICKS: &[(i32, i32)] = &[(0, 0)];
+        const SIDE_KICKS: &[(i32, i32)] = &[(0, 0), (1, 0), (-1, 0)];
+
+        // the block, the turn, the cells in the way of the turned block and the kicks it tries
+        type KickCase<'a> = (
+            BlockType,
+            Rotation,
+            Rotation,
+            &'a [(i32, i32)],
+            &'a [(i32, i32)],
+        );
+
+        #[rustfmt::skip]
+        let table: &[KickCase] = &[
+            (BlockType::T, Rotation::Rot0, Rotation::Rot1, &[], SIDE_KICKS),
+            // blocked above the stem, in the center column
+            (BlockType::T, Rotation::Rot0, Rotation::Rot1, &[(1, 0)], NO_KICKS),
+            (BlockType::T, Rotation::Rot0, Rotation::Rot3, &[(1, 0)], NO_KICKS),
+            (BlockType::J, Rotation::Rot0, Rotation::Rot1, &[(0, 2)], SIDE_KICKS),
+            // the center column cell comes first reading left to right, top to bottom
+            (BlockType::J, Rotation::Rot0, Rotation::Rot1, &[(1, 0), (0, 2)], NO_KICKS),
+            (BlockType::L, Rotation::Rot0, Rotation::Rot3, &[(2, 2)], SIDE_KICKS),
+            (BlockType::L, Rotation::Rot0, Rotation::Rot3, &[(1, 0), (2, 2)], NO_KICKS),
+            (BlockType::L, Rotation::Rot0, Rotation::Rot1, &[(0, 0), (1, 0)], SIDE_KICKS),
+            // only L, J and T follow the center column rule
+            (BlockType::S, Rotation::Rot0, Rotation::Rot1, &[(1, 1)], SIDE_KICKS),
+            (BlockType::Z, Rotation::Rot1, Rotation::Rot2, &[(1, 2)], SIDE_KICKS),
+            // I blocks never kick
+            (BlockType::I, Rotation::Rot0, Rotation::Rot1, &[(2, 0)], NO_KICKS),
+            (BlockType::O, Rotation::Rot0, Rotation::Rot1, &[], NO_KICKS),
+        ];
+
+        for (block_type, rot, dest_rot, occupied_cells, kicks) in table.iter() {
+            let block = Block {
+                rot: *rot,
+                ..Block::spawn(*block_type, &ARS)
+            };
+            let occupied_cells = vec2s(occupied_cells);
+            assert_eq!(
+                ARS.kicks(block, *dest_rot, &|cell| occupied_cells.contains(&cell)),
+                vec2s(kicks),
+                "{:?} {:?} >> {:?} blocked by {:?}",
+                block_type,
+                rot,
+                dest_rot,
+                occupied_cells
+            );
+        }
+    }
+
+    #[test]
+    fn test_full_t_spin_kicks() {
+        // only SRS has a kick which always makes a full T-spin, and it's the last one
+        type FullTSpinKickCase = (&'static dyn RotationSystem, [bool; 5]);
+        let cases: [FullTSpinKickCase; 3] = [
+            (&SRS, [false, false, false, false, true]),
+            (&ARS, [false; 5]),
+            (&CLASSIC, [false; 5]),
+        ];
+        for (system, expected) in cases {
+            for (kick_index, is_full) in expected.iter().enumerate() {
+                assert_eq!(
+                    system.is_full_t_spin_kick(kick_index),
+                    *is_full,
+                    "{:?} kick {}",
+                    system,
+                    kick_index
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_classic_kicks() {
+        for block_type in BLOCKTYPES.iter() {
+            let block = Block::spawn(*block_type, &CLASSIC);
+            for relative_rotation in [-1, 1].iter() {
+                let dest_rot = block.rotate(*relative_rotation).unwrap().rot;
+                assert_eq!(CLASSIC.kicks(block, dest_rot, &|_| true), vec2s(&[(0, 0)]));
+            }
+        }
+
+        // I, S and Z only flip between two layouts
+        for block_type in [BlockType::I, BlockType::S, BlockType::Z].iter() {
+            assert_eq!(
+                CLASSIC.cells(*block_type, Rotation::Rot0),
+                CLASSIC.cells(*block_type, Rotation::Rot2)
+            );
+            assert_eq!(
+                CLASSIC.cells(*block_type, Rotation::Rot1),
+                CLASSIC.cells(*block_type, Rotation::Rot3)
+            );
+        }
+    }
+
+    #[test]
+    fn test_rotation_kicks_in_game() {
+        // each row turns a block right from (3, 2) on an open 8x8 board with the given cells
+        // filled, and lists where it ends up if the turn works
+        type RotateCase<'a> = (
+            &'static dyn RotationSystem,
+            BlockType,
+            &'a [(i32, i32)],
+            Option<(Rotation, Vec2)>,
+        );
+
+        #[rustfmt::skip]
+        let table: &[RotateCase] = &[
+            // the SRS T kicks left off a cell under its new stem
+            (&SRS, BlockType::T, &[(4, 4)], Some((Rotation::Rot1, Vec2 { x: 2, y: 2 }))),
+            // a classic T spawns pointing down and can't kick off a cell above its new stem
+            (&CLASSIC, BlockType::T, &[(4, 2)], None),
+            (&CLASSIC, BlockType::T, &[], Some((Rotation::Rot3, Vec2 { x: 3, y: 2 }))),
+            // the ARS J kicks right when the first cell in the way is off the center column...
+            (&ARS, BlockType::J, &[(3, 4)], Some((Rotation::Rot1, Vec2 { x: 4, y: 2 }))),
+            // ...but not when it's in the center column
+            (&ARS, BlockType::J, &[(4, 2), (3, 4)], None),
+            (&ARS, BlockType::T, &[(4, 2)], None),
+            (&ARS, BlockType::I, &[(5, 5)], None),
+            (&ARS, BlockType::I, &[], Some((Rotation::Rot1, Vec2 { x: 3, y: 2 }))),
+        ];
+
+        for (rotation_system, block_type, filled_cells, expected) in table.iter() {
+            let mut board = vec![vec![false; 8]; 8];
+            for (x, y) in filled_cells.iter() {
+                board[*y as usize][*x as usize] = true;
+            }
+
+            let block = Block::spawn(*block_type, *rotation_system);
+            let block_pos = Vec2 { x: 3, y: 2 };
+            let mut game_state = test_board_from_seed(&board, block, block_pos, 0, 0);
+            game_state.rotate_block(1).unwrap();
+
+            let (rotated_block, rotated_block_pos) = game_state.active_block().unwrap();
+            let message = format!("{:?} {:?} {:?}", rotation_system, block_type, filled_cells);
+            match expected {
+                Some((rot, pos)) => {
+                    assert_eq!(rotated_block.rot, *rot, "{}", message);
+                    assert_eq!(rotated_block_pos, *pos, "{}", message);
+                }
+                None => {
+                    assert_eq!(rotated_block.rot, block.rot, "{}", message);
+                    assert_eq!(rotated_block_pos, block_pos, "{}", message);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_rotate_against_floor_and_walls() {
+        // each row turns a block resting against the floor or a wall of an empty 8x8 board and
+        // lists where it ends up
+        type RotateCase = (BlockType, Rotation, Vec2, i32, Rotation, Vec2);
+
+        #[rustfmt::skip]
+        let table: &[RotateCase] = &[
+            // on the floor
+            (BlockType::T, Rotation::Rot1, Vec2 { x: 2, y: 5 }, 1, Rotation::Rot2, Vec2 { x: 2, y: 5 }),
+            (BlockType::I, Rotation::Rot0, Vec2 { x: 2, y: 6 }, 1, Rotation::Rot1, Vec2 { x: 3, y: 4 }),
+            // flush against the left wall
+            (BlockType::T, Rotation::Rot0, Vec2 { x: 0, y: 2 }, -1, Rotation::Rot3, Vec2 { x: 0, y: 2 }),
+            (BlockType::T, Rotation::Rot1, Vec2 { x: -1, y: 2 }, -1, Rotation::Rot0, Vec2 { x: 0, y: 2 }),
+            // flush against the right wall
+            (BlockType::T, Rotation::Rot0, Vec2 { x: 5, y: 2 }, 1, Rotation::Rot1, Vec2 { x: 5, y: 2 }),
+            (BlockType::T, Rotation::Rot3, Vec2 { x: 6, y: 2 }, 1, Rotation::Rot0, Vec2 { x: 5, y: 2 }),
+        ];
+
+        for (block_type, rot, pos, relative_rotation, rotated_rot, rotated_pos) in table.iter() {
+            let board = vec![vec![false; 8]; 8];
+            let block = Block {
+                rot: *rot,
+                ..Block::new(*block_type)
+            };
+            let mut game_state = test_board_from_seed(&board, block, *pos, 0, 0);
+            assert!(game_state.is_valid_position(block, *pos));
+
+            game_state.rotate_block(*relative_rotation).unwrap();
+            let (rotated_block, rotated_block_pos) = game_state.active_block().unwrap();
+            let message = format!("{:?} {:?} at {:?}", block_type, rot, pos);
+            assert_eq!(rotated_block.rot, *rotated_rot, "{}", message);
+            assert_eq!(rotated_block_pos, *rotated_pos, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_relative_rotations() {
+        #[rustfmt::skip]
+        let table: &[(Rotation, i32, Rotation)] = &[
+            (Rotation::Rot0, 0, Rotation::Rot0),
+            (Rotation::Rot0, 1, Rotation::Rot1),
+            (Rotation::Rot0, -1, Rotation::Rot3),
+            (Rotation::Rot3, 1, Rotation::Rot0),
+            (Rotation::Rot0, 2, Rotation::Rot2),
+            (Rotation::Rot1, 2, Rotation::Rot3),
+            (Rotation::Rot3, -2, Rotation::Rot1),
+        ];
+
+        for (rot, relative_rotation, rotated_rot) in table.iter() {
+            assert_eq!(rot.rotate(*relative_rotation), Ok(*rotated_rot));
+        }
+
+        for relative_rotation in [3, -3, 4, i32::MAX, i32::MIN].iter() {
+            assert!(Rotation::Rot0.rotate(*relative_rotation).is_err());
+            assert!(Block::new(BlockType::T).rotate(*relative_rotation).is_err());
+        }
+    }
+
+    #[test]
+    fn test_rotate_180() {
+        // the SRS+ half turn table with y flipped to point down
+        #[rustfmt::skip]
+        let table: &[(Rotation, [(i32, i32); 6])] = &[
+            (Rotation::Rot0, [(0, 0), (0, -1), (1, -1), (-1, -1), (1, 0), (-1, 0)]),
+            (Rotation::Rot1, [(0, 0), (1, 0), (1, -2), (1, -1), (0, -2), (0, -1)]),
+            (Rotation::Rot2, [(0, 0), (0, 1), (-1, 1), (1, 1), (-1, 0), (1, 0)]),
+            (Rotation::Rot3, [(0, 0), (-1, 0), (-1, -2), (-1, -1), (0, -2), (0, -1)]),
+        ];
+
+        for block_type in [BlockType::T, BlockType::I, BlockType::S].iter() {
+            for (rot, kicks) in table.iter() {
+                let block = Block {
+                    rot: *rot,
+                    ..Block::new(*block_type)
+                };
+                let dest_rot = rot.rotate(2).unwrap();
+                assert_eq!(
+                    SRS.kicks(block, dest_rot, &|_| false),
+                    vec2s(kicks),
+                    "{:?} {:?}",
+                    block_type,
+                    rot
+                );
+            }
+        }
+
+        // a T flipped over a cell under its center kicks up a row
+        let mut board = vec![vec![false; 8]; 8];
+        board[4][4] = true;
+        let block = Block::new(BlockType::T);
+        let mut game_state = test_board_from_seed(&board, block, Vec2 { x: 3, y: 2 }, 0, 0);
+        game_state.rotate_block(2).unwrap();
+        let (rotated_block, rotated_block_pos) = game_state.active_block().unwrap();
+        assert_eq!(rotated_block.rot, Rotation::Rot2);
+        assert_eq!(rotated_block_pos, Vec2 { x: 3, y: 1 });
+
+        // turning the other way round ends up the same way up
+        game_state.rotate_block(-2).unwrap();
+        assert_eq!(game_state.active_block().unwrap().0.rot, Rotation::Rot0);
+
+        // anything more than a half turn is an error and leaves the block alone
+        let (block, block_pos) = game_state.active_block().unwrap();
+        assert!(game_state.rotate_block(3).is_err());
+        let (unrotated_block, unrotated_block_pos) = game_state.active_block().unwrap();
+        assert_eq!(unrotated_block.rot, block.rot);
+        assert_eq!(unrotated_block_pos, block_pos);
+    }
+
+    #[test]
+    fn test_rotate_180_action() {
+        assert_eq!(Action::from_name("rotate_180"), Some(Action::Rotate180));
+        assert_eq!(
+            KeyBindings::default().control_for(Key::Char('r'), ControlContext::Game),
+            Some(Control::Rotate180)
+        );
+
+        let mut input_handler = InputHandler::new(Handling::default());
+        input_handler.press(Control::Rotate180);
+        assert_eq!(
+            input_handler.update(std::time::Duration::from_millis(16)),
+            vec![Action::Rotate180]
+        );
+
+        let board = vec![vec![false; 8]; 8];
+        let block = Block::new(BlockType::J);
+        let mut game_state = test_board_from_seed(&board, block, Vec2 { x: 3, y: 2 }, 0, 0);
+        Action::Rotate180.apply(&mut game_state);
+        assert_eq!(game_state.active_block().unwrap().0.rot, Rotation::Rot2);
+    }
+
+    #[test]
+    fn test_is_valid_position() {
+        let board = [
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![true, false, false, false],
+        ];
+        let game_state = GameStateBuilder::from_board(&board).build();
+
+        // - 0 -
+        // 1 2 3
+        let block = Block::new(BlockType::T);
+        assert!(game_state.is_valid_position(block, Vec2 { x: 0, y: 1 }));
+        assert!(game_state.is_valid_position(block, Vec2 { x: 1, y: 2 }));
+        // above the top of the board is open
+        assert!(game_state.is_valid_position(block, Vec2 { x: 0, y: -2 }));
+        // through the walls or the floor
+        assert!(!game_state.is_valid_position(block, Vec2 { x: -1, y: 1 }));
+        assert!(!game_state.is_valid_position(block, Vec2 { x: 2, y: 1 }));
+        assert!(!game_state.is_valid_position(block, Vec2 { x: 1, y: 3 }));
+        // on a settled cell
+        assert!(!game_state.is_valid_position(block, Vec2 { x: 0, y: 2 }));
+    }
+
+    #[test]
+    fn test_lock_against_ceiling() {
+        // ARS and classic T blocks spawn with an empty row at the top of their bounding box, so
+        // they can lock with it above the board and every cell on it
+        let mut board = vec![vec![true; 10]; 2];
+        board[0].fill(false);
+        board[1][4] = false;
+        board[1][9] = false;
+
+        for rotation_system in [&ARS as &'static dyn RotationSystem, &CLASSIC] {
+            let mut game_state = GameStateBuilder::from_board(&board)
+                .rotation_system(rotation_system)
+                .active_block(
+                    Block::spawn(BlockType::T, rotation_system),
+                    Vec2 { x: 3, y: -1 },
+                )
+                .lock_delay(LockDelay::instant())
+                .build();
+
+            game_state.hard_drop();
+            assert!(!game_state.is_game_over(), "{:?}", rotation_system);
+            assert_eq!(game_state.get_settled_piece_count(), 12);
+        }
+    }
+
+    #[test]
+    fn test_blocked_spawn_tops_out() {
+        // the I block spawns into the top row of the board, where a settled cell is in the way
+        let mut board = vec![vec![false; 10]; 6];
+        board[0][4] = true;
+        let mut game_state = GameStateBuilder::from_board(&board)
+            .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::I as usize),
+            ))))
+            .build();
+
+        tick(&mut game_state);
+        assert_eq!(
+            game_state.game_over_reason(),
+            Some(GameOverReason::ToppedOut)
+        );
+    }
+
+    #[test]
+    fn test_initial_rotation() {
+        let mut game_state = default_test_board(Box::new(mocks::SequenceRangeRng::new(&[
+            BlockType::T as usize,
+            BlockType::L as usize,
+        ])));
+        tick(&mut game_state);
+        let (_, spawn_pos) = game_state.active_block().unwrap();
+
+        // turning while waiting for the next block turns it as it spawns
+        game_state.hard_drop();
+        assert!(game_state.active_block().is_none());
+        game_state.rotate_block(1).unwrap();
+        assert!(game_state.rotate_block(3).is_err());
+        tick(&mut game_state);
+        let (active_block, active_block_pos) = game_state.active_block().unwrap();
+        assert_eq!(active_block.block_type, BlockType::L);
+        assert_eq!(active_block.rot, Rotation::Rot1);
+        assert_eq!(active_block_pos, spawn_pos);
+
+        // the turn is only used once
+        game_state.hard_drop();
+        tick(&mut game_state);
+        assert_eq!(game_state.active_block().unwrap().0.rot, Rotation::Rot0);
+    }
+
+    #[test]
+    fn test_initial_hold() {
+        let mut game_state = default_test_board(Box::new(mocks::SequenceRangeRng::new(&[
+            BlockType::T as usize,
+            BlockType::S as usize,
+            BlockType::L as usize,
+        ])));
+        tick(&mut game_state);
+
+        // holding while waiting for the next block puts it straight into the hold slot
+        game_state.hard_drop();
+        game_state.hold();
+        assert!(game_state.hold_block().is_none());
+        tick(&mut game_state);
+        assert_eq!(game_state.hold_block().unwrap().block_type, BlockType::S);
+        assert_eq!(
+            game_state.active_block().unwrap().0.block_type,
+            BlockType::L
+        );
+        assert!(!game_state.can_hold());
+    }
+
+    #[test]
+    fn test_initial_rotation_avoids_top_out() {
+        // the I block spawns flat in the top row unless it's turned upright
+        let i_block_game = |board: &[Vec<bool>]| {
+            GameStateBuilder::from_board(board)
+                .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                    mocks::SingleValueRangeRng::new(BlockType::I as usize),
+                ))))
+                .build()
+        };
+
+        // a cell in the way of the flat block but not the upright one tops the game out...
+        let mut board = vec![vec![false; 10]; 6];
+        board[0][3] = true;
+        let mut game_state = i_block_game(&board);
+        tick(&mut game_state);
+        game_state.hard_drop();
+        assert_eq!(
+            game_state.game_over_reason(),
+            Some(GameOverReason::ToppedOut)
+        );
+
+        // ...unless the block is turned upright as it spawns
+        let mut game_state = i_block_game(&board);
+        game_state.rotate_block(1).unwrap();
+        tick(&mut game_state);
+        let (active_block, active_block_pos) = game_state.active_block().unwrap();
+        assert_eq!(active_block.rot, Rotation::Rot1);
+        assert!(game_state.is_valid_position(active_block, active_block_pos));
+        game_state.hard_drop();
+        assert!(!game_state.is_game_over());
+        assert_eq!(game_state.get_settled_piece_count(), 5);
+
+        // a turn which doesn't fit is ignored
+        let mut board = vec![vec![false; 10]; 6];
+        board[1][5] = true;
+        let mut game_state = i_block_game(&board);
+        game_state.rotate_block(1).unwrap();
+        tick(&mut game_state);
+        assert_eq!(game_state.active_block().unwrap().0.rot, Rotation::Rot0);
+    }
+
+    // Plays a game through an input handler the way run_game does, a 10ms frame at a time
+    fn run_game_frames(
+        game_state: &mut GameState,
+        input_handler: &mut InputHandler,
+        presses: &[(u64, Control)],
+        end_millis: u64,
+    ) {
+        const FRAME_TIME: std::time::Duration = std::time::Duration::from_millis(10);
+
+        let mut next_press = 0;
+        for frame_millis in (0..=end_millis).step_by(FRAME_TIME.as_millis() as usize) {
+            while next_press < presses.len() && presses[next_press].0 <= frame_millis {
+                input_handler.press(presses[next_press].1);
+                next_press += 1;
+            }
+
+            let mut frame_actions = input_handler.update(FRAME_TIME);
+            if game_state.active_block().is_none() {
+                for action in input_handler.held_spawn_actions() {
+                    if !frame_actions.contains(&action) {
+                        frame_actions.push(action);
+                    }
+                }
+            }
+
+            for action in frame_actions {
+                action.apply(game_state);
+            }
+
+            let was_waiting_to_spawn = game_state.active_block().is_none();
+            game_state.update(FRAME_TIME);
+            if was_waiting_to_spawn && game_state.active_block().is_some() {
+                input_handler.block_changed();
+            }
+        }
+    }
+
+    #[test]
+    fn test_held_spawn_actions() {
+        let mut input_handler = InputHandler::new(Handling::default());
+        assert!(input_handler.held_spawn_actions().is_empty());
+
+        // keys count as held from their press until they time out
+        input_handler.press(Control::RotateRight);
+        input_handler.press(Control::Hold);
+        input_handler.press(Control::MoveLeft);
+        input_handler.update(std::time::Duration::from_millis(16));
+        assert_eq!(
+            input_handler.held_spawn_actions(),
+            vec![Action::RotateRight, Action::Hold]
+        );
+        input_handler.update(std::time::Duration::from_millis(600));
+        assert!(input_handler.held_spawn_actions().is_empty());
+
+        // but a tap followed by a hard drop was for the dropped block
+        let mut input_handler = InputHandler::new(Handling::default());
+        input_handler.press(Control::RotateRight);
+        input_handler.press(Control::HardDrop);
+        input_handler.press(Control::Hold);
+        input_handler.update(std::time::Duration::from_millis(16));
+        assert_eq!(input_handler.held_spawn_actions(), vec![Action::Hold]);
+
+        // keys the terminal is repeating are being held down
+        let mut presses = held_key_presses(Control::RotateRight, 0, 600);
+        presses.extend(held_key_presses(Control::Hold, 0, 600));
+        presses.extend(held_key_presses(Control::MoveLeft, 0, 600));
+        presses.sort_by_key(|(millis, _)| *millis);
+        let mut input_handler = InputHandler::new(Handling::default());
+        let mut next_press = 0;
+        for frame_millis in (0..=590).step_by(10) {
+            while next_press < presses.len() && presses[next_press].0 <= frame_millis {
+                input_handler.press(presses[next_press].1);
+                next_press += 1;
+            }
+            input_handler.update(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(
+            input_handler.held_spawn_actions(),
+            vec![Action::RotateRight, Action::Hold]
+        );
+    }
+
+    #[test]
+    fn test_rotate_held_through_lock() {
+        let mut game_state = GameStateBuilder::new(10, 20)
+            .active_block(Block::new(BlockType::O), Vec2 { x: 3, y: 18 })
+            .lock_delay(LockDelay {
+                duration: std::time::Duration::from_millis(100),
+                max_resets: Some(15),
+            })
+            .spawn_delay(SpawnDelay::standard())
+            .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::I as usize),
+            ))))
+            .build();
+        let mut input_handler = InputHandler::new(Handling::default());
+
+        // the key is held from before the lock until the next block spawns, all well before the
+        // terminal starts repeating it
+        let presses = held_key_presses(Control::RotateLeft, 50, 300);
+        run_game_frames(&mut game_state, &mut input_handler, &presses, 300);
+        let (active_block, _) = game_state.active_block().unwrap();
+        assert_eq!(active_block.block_type, BlockType::I);
+        assert_eq!(active_block.rot, Rotation::Rot3);
+    }
+
+    #[test]
+    fn test_tap_rotate_then_hard_drop() {
+        let mut game_state = GameStateBuilder::new(10, 20)
+            .active_block(Block::new(BlockType::T), Vec2 { x: 3, y: 0 })
+            .spawn_delay(SpawnDelay::standard())
+            .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::I as usize),
+            ))))
+            .build();
+        let mut input_handler = InputHandler::new(Handling::default());
+
+        // the tap turns the block it was made on and not the next one
+        run_game_frames(
+            &mut game_state,
+            &mut input_handler,
+            &[(0, Control::RotateRight), (100, Control::HardDrop)],
+            400,
+        );
+        let (active_block, _) = game_state.active_block().unwrap();
+        assert_eq!(active_block.block_type, BlockType::I);
+        assert_eq!(active_block.rot, Rotation::Rot0);
+        assert!(game_state.hold_block().is_none());
+
+        // and neither does a tap of hold
+        let mut input_handler = InputHandler::new(Handling::default());
+        run_game_frames(
+            &mut game_state,
+            &mut input_handler,
+            &[(0, Control::Hold), (100, Control::HardDrop)],
+            400,
+        );
+        assert_eq!(game_state.hold_block().unwrap().block_type, BlockType::I);
+        assert!(game_state.can_hold());
+    }
+
+    #[test]
+    fn test_spawn_delays() {
+        let board = [
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![true, false, false, false],
+            vec![true, true, false, false],
+            vec![true, true, false, false],
+        ];
+
+        let mut game_state = GameStateBuilder::from_board(&board)
+            .active_block(Block::new(BlockType::O), Vec2 { x: 1, y: 0 })
+            .lock_delay(LockDelay::instant())
+            .spawn_delay(SpawnDelay {
+                entry: std::time::Duration::from_millis(100),
+                line_clear: std::time::Duration::from_millis(300),
+            })
+            .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::T as usize),
+            ))))
+            .build();
+
+        // the cleared rows stay on the board while the line clear delay runs
+        game_state.hard_drop();
+        assert!(game_state.active_block().is_none());
+        assert_eq!(game_state.clearing_rows(), &[5, 6]);
+        assert_eq!(game_state.get_settled_piece_count(), 9);
+        assert_eq!(game_state.line_clear_progress(), 0.0);
+
+        game_state.update(std::time::Duration::from_millis(150));
+        assert_eq!(game_state.clearing_rows(), &[5, 6]);
+        assert_eq!(game_state.line_clear_progress(), 0.5);
+
+        // a turn during the line clear delay is forgotten by the time the next block spawns
+        game_state.rotate_block(1).unwrap();
+
+        // then they're removed and the entry delay starts
+        game_state.update(std::time::Duration::from_millis(150));
+        assert!(game_state.clearing_rows().is_empty());
+        assert_eq!(game_state.line_clear_progress(), 0.0);
+        assert_eq!(game_state.get_settled_piece_count(), 1);
+        assert!(game_state.active_block().is_none());
+
+        game_state.update(std::time::Duration::from_millis(50));
+        assert!(game_state.active_block().is_none());
+
+        // only a turn made as the block spawns is used
+        game_state.rotate_block(-1).unwrap();
+        game_state.update(std::time::Duration::from_millis(50));
+        let (active_block, _) = game_state.active_block().unwrap();
+        assert_eq!(active_block.block_type, BlockType::T);
+        assert_eq!(active_block.rot, Rotation::Rot3);
+    }
+
+    #[test]
+    fn test_tap_during_entry_delay() {
+        let mut game_state = GameStateBuilder::new(10, 20)
+            .active_block(Block::new(BlockType::T), Vec2 { x: 3, y: 0 })
+            .spawn_delay(SpawnDelay::standard())
+            .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::L as usize),
+            ))))
+            .build();
+        game_state.hard_drop();
+
+        // rotate and hold tapped and let go of while the next block waits to spawn do nothing
+        game_state.update(std::time::Duration::from_millis(10));
+        game_state.rotate_block(1).unwrap();
+        game_state.hold();
+        for _ in 0..9 {
+            game_state.update(std::time::Duration::from_millis(10));
+        }
+
+        let (active_block, _) = game_state.active_block().unwrap();
+        assert_eq!(active_block.block_type, BlockType::L);
+        assert_eq!(active_block.rot, Rotation::Rot0);
+        assert!(game_state.hold_block().is_none());
+    }
+
+    #[test]
+    fn test_spawn_delays_carry_over_between_updates() {
+        // a block which clears a line as soon as it's dropped
+        let mut board = vec![vec![false; 10]; 20];
+        board[19][..6].fill(true);
+        let new_game = || {
+            GameStateBuilder::from_board(&board)
+                .active_block(Block::new(BlockType::I), Vec2 { x: 6, y: 0 })
+                .spawn_delay(SpawnDelay::standard())
+                .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                    mocks::SingleValueRangeRng::new(BlockType::T as usize),
+                ))))
+                .build()
+        };
+
+        // one long update plays out the line clear, the entry delay and the next block's fall
+        let mut one_update = new_game();
+        one_update.hard_drop();
+        one_update.update(std::time::Duration::from_millis(1000));
+
+        // the same as lots of short ones
+        let mut many_updates = new_game();
+        many_updates.hard_drop();
+        for _ in 0..100 {
+            many_updates.update(std::time::Duration::from_millis(10));
+        }
+
+        let (_, one_update_pos) = one_update.active_block().unwrap();
+        let (_, many_updates_pos) = many_updates.active_block().unwrap();
+        assert_eq!(one_update_pos, many_updates_pos);
+
+        // 300ms clearing the line and 100ms waiting to spawn leave 600ms to fall 2 rows
+        assert_eq!(one_update_pos.y, -2 + 2);
+    }
+
+    #[test]
+    fn test_lock_delay_carries_over_between_updates() {
+        // a block resting where it clears a line once the lock delay runs out
+        let mut board = vec![vec![false; 10]; 20];
+        board[19][..6].fill(true);
+        let new_game = || {
+            let mut game_state = GameStateBuilder::from_board(&board)
+                .active_block(Block::new(BlockType::I), Vec2 { x: 6, y: 0 })
+                .lock_delay(LockDelay::guideline())
+                .spawn_delay(SpawnDelay::standard())
+                .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                    mocks::SingleValueRangeRng::new(BlockType::T as usize),
+                ))))
+                .build();
+            game_state.quick_drop();
+            game_state
+        };
+
+        // one long update plays out the lock delay, the line clear, the entry delay and the next
+        // block's fall
+        let mut one_update = new_game();
+        one_update.update(std::time::Duration::from_millis(1500));
+
+        // the same as lots of short ones
+        let mut many_updates = new_game();
+        for _ in 0..150 {
+            many_updates.update(std::time::Duration::from_millis(10));
+        }
+
+        let (_, one_update_pos) = one_update.active_block().unwrap();
+        let (_, many_updates_pos) = many_updates.active_block().unwrap();
+        assert_eq!(one_update_pos, many_updates_pos);
+
+        // 500ms locking, 300ms clearing the line and 100ms waiting to spawn leave 600ms to fall
+        // 2 rows
+        assert_eq!(one_update_pos.y, -2 + 2);
+    }
+
+    #[test]
+    fn test_perfect_clear_with_line_clear_delay() {
+        let board = [
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![true, true, false, false],
+            vec![true, true, false, false],
+        ];
+
+        let mut game_state = GameStateBuilder::from_board(&board)
+            .active_block(Block::new(BlockType::O), Vec2 { x: 1, y: 0 })
+            .lock_delay(LockDelay::instant())
+            .spawn_delay(SpawnDelay::standard())
+            .build();
+
+        // the perfect clear is scored as soon as the block locks, before the rows are removed
+        game_state.hard_drop();
+        assert_eq!(game_state.get_settled_piece_count(), 8);
+        assert!(game_state
+            .take_events()
+            .iter()
+            .any(|event| matches!(event, GameEvent::PerfectClear { lines: 2, .. })));
+
+        game_state.update(SpawnDelay::standard().line_clear);
+        assert_eq!(game_state.get_settled_piece_count(), 0);
+    }
+
+    #[test]
+    fn test_das_charges_during_entry_delay() {
+        let mut game_state = GameStateBuilder::new(10, 20)
+            .spawn_delay(SpawnDelay {
+                entry: std::time::Duration::from_millis(600),
+                line_clear: std::time::Duration::from_millis(0),
+            })
+            .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::T as usize),
+            ))))
+            .build();
+        let mut input_handler = InputHandler::new(Handling {
+            arr: std::time::Duration::from_millis(0),
+            ..Handling::default()
+        });
+
+        // DAS charges while the first block waits to spawn so it shifts to the wall straight away
+        run_game_frames(
+            &mut game_state,
+            &mut input_handler,
+            &held_key_presses(Control::MoveLeft, 0, 1000),
+            610,
+        );
+
+        assert!(game_state.active_block().is_some());
+        assert_eq!(active_block_distance_to_left_wall(&game_state), 0);
+    }
+
+    #[test]
+    fn test_game_spawns_with_rotation_system() {
+        let mut game_state = GameStateBuilder::new(10, 20)
+            .rotation_system(&CLASSIC)
+            .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SequenceRangeRng::new(&[BlockType::T as usize, BlockType::S as usize]),
+            ))))
+            .build();
+
+        tick(&mut game_state);
+        let (active_block, _) = game_state.active_block().unwrap();
+        assert_eq!(active_block.block_type, BlockType::T);
+        assert_eq!(active_block.rot, Rotation::Rot2);
+        assert_eq!(
+            active_block.cells(),
+            CLASSIC.cells(BlockType::T, Rotation::Rot2)
+        );
+
+        // held blocks come back in the system's spawn orientation
+        game_state.rotate_block(1).unwrap();
+        game_state.hold();
+        let hold_block = game_state.hold_block().unwrap();
+        assert_eq!(hold_block.rot, Rotation::Rot2);
+        assert_eq!(game_state.active_block().unwrap().0.rot, Rotation::Rot0);
+    }
+
+    fn take_line_clears(game_state: &mut GameState) -> Vec<LineClear> {
+        game_state
+            .take_events()
+            .iter()
+            .filter_map(|event| match event {
+                GameEvent::LineClear(line_clear) => Some(*line_clear),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_combo() {
+        // every horizontal I block clears a line on a board exactly as wide as it
+        let board = vec![vec![false; 4]; 8];
+        let active_block = Block {
+            rot: Rotation::Rot0,
+            ..Block::new(BlockType::I)
+        };
+        let mut game_state = GameState::make_from_seed(
+            &board,
+            active_block,
+            Vec2 { x: 0, y: 0 },
+            0,
+            0,
+            LockDelay::instant(),
+            Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::I as usize),
+            ))),
+        );
+
+        for expected_combo in 1..=3 {
+            game_state.hard_drop();
+            assert_eq!(game_state.combo(), expected_combo);
+
+            let line_clears = take_line_clears(&mut game_state);
+            assert_eq!(line_clears.len(), 1);
+            assert_eq!(line_clears[0].combo, expected_combo);
+            assert_eq!(line_clears[0].score, 100 + 50 * (expected_combo - 1));
+
+            // spawn the next block
+            tick(&mut game_state);
+        }
+
+        // a block which doesn't clear any lines breaks the combo
+        game_state.rotate_block(1).unwrap();
+        game_state.hard_drop();
+        assert_eq!(game_state.combo(), 0);
+        assert_eq!(take_line_clears(&mut game_state), vec![]);
+
+        // so the next clear starts a new combo
+        tick(&mut game_state);
+        game_state.hard_drop();
+        let line_clears = take_line_clears(&mut game_state);
+        assert_eq!(line_clears.len(), 1);
+        assert_eq!(line_clears[0].combo, 1);
+    }
+
+    #[test]
+    fn test_back_to_back() {
+        let mut board = vec![vec![false; 4]; 10];
+        for row in board.iter_mut().skip(2) {
+            *row = vec![false, true, true, true];
+        }
+
+        // drop a vertical I block down the left column
+        let active_block = Block {
+            rot: Rotation::Rot3,
+            ..Block::new(BlockType::I)
+        };
+        let mut game_state = GameState::make_from_seed(
+            &board,
+            active_block,
+            Vec2 { x: -1, y: 0 },
+            0,
+            0,
+            LockDelay::instant(),
+            Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::I as usize),
+            ))),
+        );
+
+        // the first tetris starts the back-to-back chain but doesn't get the bonus
+        game_state.hard_drop();
+        let line_clears = take_line_clears(&mut game_state);
+        assert_eq!(line_clears.len(), 1);
+        assert_eq!(line_clears[0].lines, 4);
+        assert!(!line_clears[0].back_to_back);
+        assert_eq!(line_clears[0].score, 800);
+        assert!(game_state.back_to_back());
+
+        // the second tetris earns the back-to-back bonus on top of the combo bonus
+        tick(&mut game_state);
+        game_state.rotate_block(-1).unwrap();
+        game_state.move_active_block_horizontal(-1);
+        game_state.hard_drop();
+        let line_clears = take_line_clears(&mut game_state);
+        assert_eq!(line_clears.len(), 1);
+        assert_eq!(line_clears[0].lines, 4);
+        assert!(line_clears[0].back_to_back);
+        assert_eq!(line_clears[0].score, 800 * 3 / 2 + 50);
+        assert!(game_state.back_to_back());
+        assert_eq!(game_state.get_settled_piece_count(), 0);
+
+        // a single isn't difficult so it ends the chain (now at level 2)
+        tick(&mut game_state);
+        game_state.hard_drop();
+        let line_clears = take_line_clears(&mut game_state);
+        assert_eq!(line_clears.len(), 1);
+        assert_eq!(line_clears[0].lines, 1);
+        assert!(!line_clears[0].back_to_back);
+        assert_eq!(line_clears[0].score, (100 + 50 * 2) * 2);
+        assert!(!game_state.back_to_back());
+    }
+
+    #[test]
+    fn test_perfect_clear() {
+        let board = [
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![true, true, false, false],
+            vec![true, true, false, false],
+        ];
+
+        // the O block fills the gap on the right and clears every settled cell on the board
+        let active_block = Block {
+            rot: Rotation::Rot0,
+            ..Block::new(BlockType::O)
+        };
+        let mut game_state = test_board_from_seed(&board, active_block, Vec2 { x: 1, y: 0 }, 0, 0);
+
+        game_state.hard_drop();
+        assert_eq!(game_state.get_settled_piece_count(), 0);
+
+        let events = game_state.take_events();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[0],
+            GameEvent::LineClear(LineClear { lines: 2, .. })
+        ));
+        assert_eq!(
+            events[1],
+            GameEvent::PerfectClear {
+                lines: 2,
+                score: 1200
+            }
+        );
+
+        // double + perfect clear bonus + 2 points for each of the 4 cells hard dropped
+        assert_eq!(game_state.score(), 300 + 1200 + 2 * 4);
+    }
+
+    #[test]
+    fn test_no_perfect_clear_with_cells_left() {
+        let board = [
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![true, false, false, false],
+            vec![true, true, false, false],
+        ];
+
+        // the O block clears the bottom row but leaves a cell behind
+        let active_block = Block {
+            rot: Rotation::Rot0,
+            ..Block::new(BlockType::O)
+        };
+        let mut game_state = test_board_from_seed(&board, active_block, Vec2 { x: 1, y: 0 }, 0, 0);
+
+        game_state.hard_drop();
+        assert_eq!(game_state.get_settled_piece_count(), 1 + 2);
+        assert!(game_state
+            .take_events()
+            .iter()
+            .all(|event| !matches!(event, GameEvent::PerfectClear { .. })));
+    }
+
+    #[test]
+    fn test_sprint_goal() {
+        // every horizontal I block clears a line on a board exactly as wide as it
+        let mut game_state = GameStateBuilder::new(4, 8)
+            .next_queue_len(1)
+            .lock_delay(LockDelay::instant())
+            .mode(GameMode::Sprint { line_goal: 2 })
+            .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::I as usize),
+            ))))
+            .build();
+
+        tick(&mut game_state);
+        game_state.hard_drop();
+        assert!(!game_state.is_game_over());
+
+        // clearing the last line ends the game and stops the clock
+        tick(&mut game_state);
+        game_state.hard_drop();
+        assert_eq!(game_state.lines_cleared(), 2);
+        assert_eq!(
+            game_state.game_over_reason(),
+            Some(GameOverReason::GoalComplete)
+        );
+
+        let final_time = game_state.elapsed_time();
+        assert_eq!(final_time, std::time::Duration::from_millis(500));
+        tick(&mut game_state);
+        assert_eq!(game_state.elapsed_time(), final_time);
+    }
+
+    #[test]
+    fn test_sprint_splits() {
+        // every horizontal I block clears a line on a board exactly as wide as it
+        let mut game_state = GameStateBuilder::new(4, 8)
+            .next_queue_len(1)
+            .lock_delay(LockDelay::instant())
+            .mode(GameMode::sprint())
+            .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::I as usize),
+            ))))
+            .build();
+
+        // a split is taken at every 10th line with the time it was cleared at
+        let mut expected_splits = vec![];
+        for lines_cleared in 1..=25 {
+            tick(&mut game_state);
+            game_state.hard_drop();
+            assert_eq!(game_state.lines_cleared(), lines_cleared);
+            if lines_cleared % GameMode::SPRINT_SPLIT_INTERVAL == 0 {
+                expected_splits.push(game_state.elapsed_time());
+            }
+            assert_eq!(game_state.splits(), &expected_splits[..]);
+        }
+
+        assert_eq!(
+            game_state.splits(),
+            &[
+                std::time::Duration::from_millis(2500),
+                std::time::Duration::from_millis(5000)
+            ]
+        );
+
+        // and the latest one is shown under the timer
+        let mut renderer = GridRenderer::new(80, 24);
+        render_game(
+            &mut renderer,
+            &GameLayout::new(24, 80, game_state.next_queue_len()),
+            &game_state,
+            1,
+            None,
+            false,
+        );
+        assert!(renderer.to_text().contains("|Time: 00:06.250|"));
+        assert!(renderer.to_text().contains("|20L: 00:05.000 |"));
+    }
+
+    #[test]
+    fn test_sprint_splits_from_a_started_game() {
+        let mut game_state = GameStateBuilder::from_board(&vec![vec![false; 4]; 4])
+            .active_block(Block::new(BlockType::I), Vec2 { x: 0, y: 0 })
+            .mode(GameMode::sprint())
+            .lines_cleared(19)
+            .lock_delay(LockDelay::instant())
+            .build();
+
+        // lines cleared before the game was set up don't count as splits of their own
+        game_state.update(std::time::Duration::from_millis(100));
+        game_state.hard_drop();
+        assert_eq!(game_state.lines_cleared(), 20);
+        assert_eq!(
+            game_state.splits(),
+            &[std::time::Duration::from_millis(100)]
+        );
+    }
+
+    #[test]
+    fn test_ultra_time_limit() {
+        let mut game_state = GameStateBuilder::new(20, 30)
+            .next_queue_len(1)
+            .lock_delay(LockDelay::instant())
+            .mode(GameMode::Ultra {
+                time_limit: std::time::Duration::from_secs(1),
+            })
+            .piece_generator(Box::new(MemorylessGenerator::new(Box::new(
+                mocks::SingleValueRangeRng::new(BlockType::T as usize),
+            ))))
+            .build();
+
+        for _ in 0..3 {
+            tick(&mut game_state);
+        }
+        assert!(!game_state.is_game_over());
+        assert_eq!(
+            game_state.remaining_time(),
+            Some(std::time::Duration::from_millis(250))
+        );
+
+        // the clock never runs past the time limit
+        game_state.update(std::time::Duration::from_millis(400));
+        assert_eq!(game_state.game_over_reason(), Some(GameOverReason::TimeUp));
+        assert_eq!(
+            game_state.remaining_time(),
+            Some(std::time::Duration::from_millis(0))
+        );
+
+        // nothing moves once time is up
+        let settled_piece_count = game_state.get_settled_piece_count();
+        tick(&mut game_state);
+        assert_eq!(game_state.get_settled_piece_count(), settled_piece_count);
+        assert!(game_state.active_block().is_some());
+    }
+
+    #[test]
+    fn test_level_calculation_1() {
+        let board = [
+            vec![false, false, false, false, false, false],
+            vec![false, false, false, false, false, false],
+            vec![false, false, false, false, false, false],
+            vec![false, false, false, false, false, false],
+            vec![false, false, false, false, false, false],
+            vec![true, false, true, true, true, true],
+            vec![true, false, true, true, true, true],
+            vec![true, false, true, true, true, true],
+            vec![true, false, true, true, true, true],
+        ];
+
+        let active_block = Block {
+            rot: Rotation::Rot3,
+            ..Block::new(BlockType::I)
+        };
+        let active_block_pos = Vec2::zero();
+
+        let start_score = 160;
+        let mut game_state =
+            test_board_from_seed(&board, active_block, active_block_pos, start_score, 4);
+
+        let start_level = 1;
+        assert_eq!(game_state.score(), start_score);
+        assert_eq!(game_state.level(), start_level);
+
+        drop_active_block(&mut game_state);
+        assert!(game_state.score() > start_score);
+        assert_eq!(game_state.level(), start_level + 1);
+    }
+
+    #[test]
+    fn test_level_calculation_2() {
+        let board = [
+            vec![false, false, false, false, false, false],
+            vec![false, false, false, false, false, false],
+            vec![false, false, false, false, false, false],
+            vec![false, false, false, false, false, false],
+            vec![false, false, false, false, false, false],
+            vec![true, false, true, true, true, true],
+            vec![true, false, true, true, true, true],
+            vec![true, false, true, true, true, true],
+            vec![true, false, true, true, true, true],
+        ];
+
+        let active_block = Block {
+            rot: Rotation::Rot3,
+            ..Block::new(BlockType::I)
+        };
+        let active_block_pos = Vec2::zero();
+
+        let start_score = 2400 + 40; // 9 lines = 2 tetrises + a single line clear
+        let mut game_state =
+            test_board_from_seed(&board, active_block, active_block_pos, start_score, 9);
+
+        let start_level = 2;
+        assert_eq!(game_state.score(), start_score);
+        assert_eq!(game_state.level(), start_level);
+
+        drop_active_block(&mut game_state);
+        assert!(game_state.score() > start_score);
+        assert_eq!(game_state.level(), start_level + 1);
+    }
+
+    #[test]
+    fn test_seeded_rng() {
+        let mut generator_a = BagGenerator::new(1, Box::new(SeededRangeRng::new(1234)));
+        let mut generator_b = BagGenerator::new(1, Box::new(SeededRangeRng::new(1234)));
+        let pieces_a = take_pieces(&mut generator_a, 21);
+        assert_eq!(pieces_a, take_pieces(&mut generator_b, 21));
+
+        let mut rng = SeededRangeRng::new(99);
+        for _ in 0..100 {
+            let value = rng.gen_range(3, 7);
+            assert!((3..7).contains(&value));
+        }
+    }
+
+    #[test]
+    fn test_daily_seed() {
+        use std::time::{Duration, UNIX_EPOCH};
+        const DAY: u64 = 24 * 60 * 60;
+
+        // every game started on the same day shares a seed
+        let morning = UNIX_EPOCH + Duration::from_secs(19_000 * DAY + 60);
+        let evening = UNIX_EPOCH + Duration::from_secs(19_000 * DAY + DAY - 60);
+        let next_day = UNIX_EPOCH + Duration::from_secs(19_001 * DAY);
+        assert_eq!(
+            SeededRangeRng::daily_seed(morning),
+            SeededRangeRng::daily_seed(evening)
+        );
+        assert_ne!(
+            SeededRangeRng::daily_seed(evening),
+            SeededRangeRng::daily_seed(next_day)
+        );
+    }
+
+    #[test]
+    fn test_input_script_parse() {
+        let script = InputScript::parse(
+            "# move over and drop\n\
+             500 hard_drop\n\
+             \n\
+             0 left\n\
+             120 rotate_right\n\
+             130 rotate_180\n",
+        )
+        .unwrap();
+
+        // actions are sorted by their timestamp
+        let expected_script =
+            InputScript::parse("0 left\n120 rotate_right\n130 rotate_180\n500 hard_drop").unwrap();
+        assert_eq!(script, expected_script);
+
+        assert!(InputScript::parse("0 teleport").is_err());
+        assert!(InputScript::parse("soon hard_drop").is_err());
+        assert!(InputScript::parse("0 hard_drop now").is_err());
+        assert!(InputScript::load("data/does_not_exist.script").is_err());
+    }
+
+    #[test]
+    fn test_simulation() {
+        let script = InputScript::parse(
+            "1000 hard_drop\n\
+             2000 left\n\
+             2000 left\n\
+             2000 hard_drop\n\
+             3000 hold\n\
+             4000 right\n\
+             4000 hard_drop",
+        )
+        .unwrap();
+
+        let result = run_simulation(42, GameMode::Marathon, &script);
+        assert_eq!(result.lines, 0);
+        assert_eq!(result.level, 1);
+        assert_eq!(result.game_over_reason, None);
+
+        // 3 locked pieces plus however much of the next piece has fallen onto the board
+        let filled_cells = result
+            .board
+            .chars()
+            .filter(|c| *c != '.' && *c != '\n')
+            .count();
+        assert!(filled_cells >= 3 * 4);
+        assert_eq!(result.board.lines().count(), 20);
+        assert!(result.board.lines().all(|row| row.len() == 10));
+
+        // the same seed and script always play out the same game
+        let replayed_result = run_simulation(42, GameMode::Marathon, &script);
+        assert_eq!(replayed_result.score, result.score);
+        assert_eq!(replayed_result.board, result.board);
+    }
+
+    #[test]
+    fn test_replay_serialization() {
+        let mut replay = Replay::new(0xDEAD_BEEF, GameMode::sprint(), RotationSystemType::Ars);
+        replay.record_frame(&[], Some(std::time::Duration::from_micros(16_667)));
+        replay.record_frame(&[Action::MoveLeft, Action::RotateRight], None);
+        replay.record_frame(&[], None);
+        replay.record_frame(&Action::ALL, Some(std::time::Duration::from_secs(1)));
+
+        // frames which do nothing aren't recorded
+        assert_eq!(replay.frames().len(), 3);
+
+        // the game is played back with the rotation system it was recorded with
+        let mut game_state = replay.new_game();
+        game_state.update(std::time::Duration::from_secs(1));
+        let (active_block, _) = game_state.active_block().unwrap();
+        assert_eq!(
+            format!("{:?}", active_block.rotation_system),
+            "ArikaRotationSystem"
+        );
+
+        let bytes = replay.to_bytes();
+        assert_eq!(Replay::from_bytes(&bytes), Ok(replay));
+
+        let ultra_replay = Replay::new(7, GameMode::ultra(), RotationSystemType::Classic);
+        assert_eq!(
+            Replay::from_bytes(&ultra_replay.to_bytes()),
+            Ok(ultra_replay)
+        );
+
+        assert!(Replay::from_bytes(b"not a replay").is_err());
+        assert!(Replay::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Replay::load("data/does_not_exist.replay").is_err());
+    }
+
+    #[test]
+    fn test_replay_playback() {
+        const FRAME_TIME: std::time::Duration = std::time::Duration::from_micros(16_667);
+
+        let mut replay = Replay::new(1234, GameMode::Marathon, RotationSystemType::Srs);
+        let mut game_state = replay.new_game();
+        for frame in 0..2000 {
+            let actions = match frame % 90 {
+                10 => vec![Action::MoveLeft, Action::RotateLeft],
+                20 => vec![Action::SoftDropStart],
+                40 => vec![Action::SoftDropStop, Action::MoveRight],
+                50 if frame % 360 == 50 => vec![Action::Hold],
+                80 => vec![Action::HardDrop],
+                _ => vec![],
+            };
+
+            for action in &actions {
+                action.apply(&mut game_state);
+            }
+
+            // pause every so often without updating the game
+            let delta_time = if frame % 100 < 5 {
+                None
+            } else {
+                Some(FRAME_TIME)
+            };
+            replay.record_frame(&actions, delta_time);
+            if let Some(delta_time) = delta_time {
+                game_state.update(delta_time);
+            }
+        }
+
+        let replay = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        let mut replayed_game_state = replay.new_game();
+        for frame in replay.frames() {
+            frame.apply(&mut replayed_game_state);
+        }
+
+        assert!(game_state.score() > 0);
+        assert_eq!(replayed_game_state.score(), game_state.score());
+        assert_eq!(
+            replayed_game_state.lines_cleared(),
+            game_state.lines_cleared()
+        );
+        assert_eq!(
+            render_board_text(&replayed_game_state),
+            render_board_text(&game_state)
+        );
+    }
+
+    #[test]
+    fn test_key_bindings_parse() {
+        // an empty config keeps every default
+        assert_eq!(KeyBindings::parse(""), Ok(KeyBindings::default()));
+
+        let bindings = KeyBindings::parse(
+            "[keys]\n\
+             # rotate with the arrow keys\n\
+             rotate_left = \"left\"\n\
+             rotate_right = [\"right\", \"up\",] # trailing commas are fine\n\
+             move_left = \"a\"\n\
+             move_right = [\"d\"]\n\
+             hard_drop = [\"space\", \",\"]\n\
+             \n\
+             [other]\n\
+             hold = \"not a key\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(bindings.keys(Control::RotateLeft), &[Key::Left]);
+        assert_eq!(bindings.keys(Control::RotateRight), &[Key::Right, Key::Up]);
+        assert_eq!(bindings.keys(Control::MoveLeft), &[Key::Char('a')]);
+        assert_eq!(
+            bindings.keys(Control::HardDrop),
+            &[Key::Char(' '), Key::Char(',')]
+        );
+        assert_eq!(
+            bindings.keys(Control::Hold),
+            KeyBindings::default().keys(Control::Hold)
+        );
+
+        // written configs read back the same
+        assert_eq!(
+            KeyBindings::parse(bindings.to_config_string()),
+            Ok(bindings)
+        );
+    }
+
+    #[test]
+    fn test_key_bindings_errors() {
+        let parse_error = |config: &str| KeyBindings::parse(config).unwrap_err();
+
+        assert!(parse_error("[keys]\nteleport = \"t\"").contains("unknown control"));
+        assert!(parse_error("[keys]\nhold = \"shift\"").contains("unknown key"));
+        assert!(parse_error("[keys]\nhold = c").starts_with("line 2"));
+        assert!(parse_error("[keys]\nhold = []").contains("no keys"));
+
+        // 'a' is already bound to move left by default
+        assert_eq!(
+            parse_error("[keys]\nhold = \"a\""),
+            "'a' is bound to both move_left and hold"
+        );
+
+        // menus are read separately from the game so they can share keys
+        assert!(KeyBindings::parse("[keys]\nmenu_select = \"space\"").is_ok());
+    }
+
+    #[test]
+    fn test_key_bindings_rebind() {
+        let mut bindings = KeyBindings::default();
+        assert_eq!(
+            bindings.control_for(Key::Char('w'), ControlContext::Game),
+            Some(Control::Hold)
+        );
+        assert_eq!(
+            bindings.control_for(Key::Char('w'), ControlContext::Menu),
+            Some(Control::MenuUp)
+        );
+
+        assert!(bindings.rebind(Control::Hold, vec![Key::Char('h')]).is_ok());
+        assert_eq!(
+            bindings.control_for(Key::Char('w'), ControlContext::Game),
+            None
+        );
+        assert_eq!(
+            bindings.control_for(Key::Char('h'), ControlContext::Game),
+            Some(Control::Hold)
+        );
+
+        // a conflicting binding leaves the old keys in place
+        assert!(bindings
+            .rebind(Control::Pause, vec![Key::Char('h')])
+            .is_err());
+        assert_eq!(bindings.keys(Control::Pause), &[Key::Char('p')]);
+    }
+
+    #[test]
+    fn test_key_bindings_toggle_key() {
+        let mut bindings = KeyBindings::default();
+
+        // adding a key keeps the other defaults
+        assert!(bindings.toggle_key(Control::Hold, Key::Char('h')).is_ok());
+        assert_eq!(
+            bindings.keys(Control::Hold),
+            &[Key::Char('w'), Key::Char('c'), Key::Char('h')]
+        );
+
+        // and so does taking one away
+        assert!(bindings.toggle_key(Control::Hold, Key::Char('w')).is_ok());
+        assert_eq!(
+            bindings.keys(Control::Hold),
+            &[Key::Char('c'), Key::Char('h')]
+        );
+        assert_eq!(
+            bindings.control_for(Key::Char('w'), ControlContext::Game),
+            None
+        );
+
+        // every other control is untouched
+        let defaults = KeyBindings::default();
+        for control in Control::ALL.iter().filter(|c| **c != Control::Hold) {
+            assert_eq!(bindings.keys(*control), defaults.keys(*control));
+        }
+
+        // a key bound elsewhere or a control's last key can't be toggled
+        assert!(bindings.toggle_key(Control::Hold, Key::Char('a')).is_err());
+        assert!(bindings.toggle_key(Control::Pause, Key::Char('p')).is_err());
+        assert_eq!(bindings.keys(Control::Pause), &[Key::Char('p')]);
+    }
+
+    #[test]
+    fn test_replay_controls() {
+        let mut bindings = KeyBindings::default();
+        assert_eq!(
+            bindings.control_for(Key::Char('2'), ControlContext::Replay),
+            Some(Control::ReplaySpeed2x)
+        );
+        assert_eq!(
+            bindings.control_for(Key::Char('.'), ControlContext::Replay),
+            Some(Control::ReplayStep)
+        );
+
+        // the game's pause and quit keys work in replays, but not its other controls
+        assert_eq!(
+            bindings.control_for(Key::Char('p'), ControlContext::Replay),
+            Some(Control::Pause)
+        );
+        assert_eq!(
+            bindings.control_for(Key::Esc, ControlContext::Replay),
+            Some(Control::Quit)
+        );
+        assert_eq!(
+            bindings.control_for(Key::Char('a'), ControlContext::Replay),
+            None
+        );
+        assert_eq!(
+            bindings.control_for(Key::Char('2'), ControlContext::Game),
+            None
+        );
+
+        // so replay controls can share keys with the game but not with pause and quit
+        assert!(bindings
+            .rebind(Control::ReplayStep, vec![Key::Char('a')])
+            .is_ok());
+        assert!(bindings
+            .rebind(Control::ReplaySpeed4x, vec![Key::Char('p')])
+            .is_err());
+        assert!(bindings
+            .rebind(Control::Pause, vec![Key::Char('1')])
+            .is_err());
+    }
+
+    #[test]
+    fn test_input_event_from_key() {
+        let bindings = KeyBindings::default();
+        assert_eq!(
+            InputEvent::from_key(Key::Char('w'), &bindings, ControlContext::Game),
+            InputEvent::Control(Control::Hold)
+        );
+        assert_eq!(
+            InputEvent::from_key(Key::Char('w'), &bindings, ControlContext::Menu),
+            InputEvent::Control(Control::MenuUp)
+        );
+        assert_eq!(
+            InputEvent::from_key(Key::Char('1'), &bindings, ControlContext::Game),
+            InputEvent::Key(Key::Char('1'))
+        );
+
+        // typed text never reads as a control
+        assert_eq!(
+            InputEvent::from_key(Key::Char('w'), &bindings, ControlContext::Text),
+            InputEvent::Key(Key::Char('w'))
+        );
+        assert_eq!(
+            InputEvent::from_key(Key::Enter, &bindings, ControlContext::Text),
+            InputEvent::Key(Key::Enter)
+        );
+    }
+
+    #[test]
+    fn test_scripted_input_source() {
+        let millis = std::time::Duration::from_millis;
+        let bindings = KeyBindings::default();
+        let mut input = ScriptedInputSource::new(vec![
+            (millis(250), InputEvent::Control(Control::HardDrop)),
+            (millis(100), InputEvent::Control(Control::MoveLeft)),
+            (millis(100), InputEvent::Key(Key::Char('1'))),
+        ]);
+        let next_event = |input: &mut ScriptedInputSource, timeout| {
+            input.next_event(&bindings, ControlContext::Game, timeout)
+        };
+
+        // events play in time order, waiting only as long as the timeout allows
+        assert_eq!(next_event(&mut input, millis(60)), None);
+        assert_eq!(input.time(), millis(60));
+        assert_eq!(
+            next_event(&mut input, millis(60)),
+            Some(InputEvent::Control(Control::MoveLeft))
+        );
+        assert_eq!(input.time(), millis(100));
+        assert_eq!(
+            next_event(&mut input, millis(0)),
+            Some(InputEvent::Key(Key::Char('1')))
+        );
+        assert_eq!(next_event(&mut input, millis(100)), None);
+        assert_eq!(input.time(), millis(200));
+        assert!(!input.is_finished());
+        assert_eq!(
+            next_event(&mut input, millis(100)),
+            Some(InputEvent::Control(Control::HardDrop))
+        );
+        assert!(input.is_finished());
+        assert_eq!(next_event(&mut input, millis(100)), None);
+        assert_eq!(input.time(), millis(350));
+    }
+
+    #[test]
+    fn test_recording_input_source() {
+        let events = [
+            InputEvent::Control(Control::MenuDown),
+            InputEvent::Control(Control::MenuSelect),
+            InputEvent::Key(Key::Char('a')),
+        ];
+        let bindings = KeyBindings::default();
+        let mut input = RecordingInputSource::new(ScriptedInputSource::immediate(&events));
+
+        let timeout = std::time::Duration::from_millis(10);
+        let mut read_events = vec![];
+        while let Some(event) = input.next_event(&bindings, ControlContext::Menu, timeout) {
+            read_events.push(event);
+        }
+        assert_eq!(read_events, events);
+
+        // the recording plays back the same events
+        let recording = input.into_events();
+        assert_eq!(recording.len(), events.len());
+        assert!(recording.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+
+        let mut playback = ScriptedInputSource::new(recording);
+        for event in events.iter() {
+            assert_eq!(
+                playback.next_event(&bindings, ControlContext::Menu, timeout),
+                Some(*event)
+            );
+        }
+        assert!(playback.is_finished());
+    }
+
+    // Runs the input handler over a timeline of key events, returning the time of each action
+    fn run_input_handler(
+        handling: Handling,
+        presses: &[(u64, Control)],
+        end_millis: u64,
+    ) -> Vec<(u64, Action)> {
+        const FRAME_MILLIS: u64 = 10;
+
+        let mut input_handler = InputHandler::new(handling);
+        let mut actions = vec![];
+        let mut next_press = 0;
+        for frame_millis in (0..=end_millis).step_by(FRAME_MILLIS as usize) {
+            while next_press < presses.len() && presses[next_press].0 <= frame_millis {
+                input_handler.press(presses[next_press].1);
+                next_press += 1;
+            }
+
+            for action in input_handler.update(std::time::Duration::from_millis(FRAME_MILLIS)) {
+                actions.push((frame_millis, action));
+            }
+        }
+
+        actions
+    }
+
+    // A key held down from `start`: the terminal reports the press, waits out its repeat delay
+    // and then repeats the key every 30ms until `end`
+    fn held_key_presses(control: Control, start: u64, end: u64) -> Vec<(u64, Control)> {
+        let mut presses = vec![(start, control)];
+        presses.extend(
+            (start + 500..end)
+                .step_by(30)
+                .map(|millis| (millis, control)),
+        );
+        presses
+    }
+
+    #[test]
+    fn test_input_handler_taps() {
+        let presses = [
+            (0, Control::MoveLeft),
+            (200, Control::MoveLeft),
+            (200, Control::RotateRight),
+            (300, Control::HardDrop),
+        ];
+
+        // every tap acts on the frame it's pressed and nothing repeats, as long as the terminal
+        // would have started repeating a held key before DAS charges
+        let handling = Handling {
+            key_repeat_delay: std::time::Duration::from_millis(50),
+            ..Handling::default()
+        };
+        assert_eq!(
+            run_input_handler(handling, &presses, 2000),
+            vec![
+                (0, Action::MoveLeft),
+                (200, Action::MoveLeft),
+                (200, Action::RotateRight),
+                (300, Action::HardDrop),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_input_handler_das_arr() {
+        let handling = Handling::default();
+        let actions = run_input_handler(
+            handling,
+            &held_key_presses(Control::MoveRight, 0, 1000),
+            2000,
+        );
+        assert!(actions
+            .iter()
+            .all(|(_, action)| *action == Action::MoveRight));
+
+        // the press shifts straight away and DAS charges from the press, without waiting for
+        // the terminal to start repeating
+        assert_eq!(actions[0], (0, Action::MoveRight));
+        assert_eq!(actions[1], (170, Action::MoveRight));
+
+        // after that it shifts every ARR until the repeats stop
+        let auto_shifts = actions.len() - 1;
+        let held_time = 1000 - handling.das.as_millis() as usize
+            + handling.key_release_time.as_millis() as usize;
+        let expected_auto_shifts = 1 + held_time / handling.arr.as_millis() as usize;
+        assert!((expected_auto_shifts - 1..=expected_auto_shifts + 1).contains(&auto_shifts));
+        assert!(actions.last().unwrap().0 < 1100);
+
+        // with no ARR the block jumps straight to the wall
+        let instant_handling = Handling {
+            arr: std::time::Duration::from_millis(0),
+            ..Handling::default()
+        };
+        let actions = run_input_handler(
+            instant_handling,
+            &held_key_presses(Control::MoveLeft, 0, 1000),
+            1000,
+        );
+        assert_eq!(
+            actions,
+            vec![(0, Action::MoveLeft), (170, Action::ShiftLeftToWall)]
+        );
+
+        // and only jumps again once the block has turned or a new one has spawned
+        let mut presses = held_key_presses(Control::MoveLeft, 0, 1000);
+        presses.push((300, Control::RotateRight));
+        presses.sort_by_key(|(millis, _)| *millis);
+        let mut input_handler = InputHandler::new(instant_handling);
+        let mut actions = vec![];
+        let mut next_press = 0;
+        for frame_millis in (0..=1000).step_by(10) {
+            while next_press < presses.len() && presses[next_press].0 <= frame_millis {
+                input_handler.press(presses[next_press].1);
+                next_press += 1;
+            }
+            if frame_millis == 600 {
+                input_handler.block_changed();
+            }
+
+            for action in input_handler.update(std::time::Duration::from_millis(10)) {
+                actions.push((frame_millis, action));
+            }
+        }
+        assert_eq!(
+            actions,
+            vec![
+                (0, Action::MoveLeft),
+                (170, Action::ShiftLeftToWall),
+                (300, Action::RotateRight),
+                (300, Action::ShiftLeftToWall),
+                (600, Action::ShiftLeftToWall),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_input_handler_held_keys() {
+        // holding rotate only rotates once
+        let actions = run_input_handler(
+            Handling::default(),
+            &held_key_presses(Control::RotateLeft, 0, 1000),
+            2000,
+        );
+        assert_eq!(actions, vec![(0, Action::RotateLeft)]);
+
+        // soft drop lasts for as long as the key is held
+        let actions = run_input_handler(
+            Handling::default(),
+            &held_key_presses(Control::SoftDrop, 0, 1000),
+            2000,
+        );
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0], (0, Action::SoftDropStart));
+        assert_eq!(actions[1].1, Action::SoftDropStop);
+        assert!((1000..1100).contains(&actions[1].0));
+
+        // pressing the other direction takes over the shift until it's let go
+        let mut presses = held_key_presses(Control::MoveLeft, 0, 2000);
+        presses.extend(held_key_presses(Control::MoveRight, 1000, 1600));
+        presses.sort_by_key(|(millis, _)| *millis);
+        let actions = run_input_handler(Handling::default(), &presses, 2000);
+        let shift_direction_at = |millis: u64| {
+            actions
+                .iter()
+                .rev()
+                .find(|(action_millis, _)| *action_millis <= millis)
+                .unwrap()
+                .1
+        };
+        assert_eq!(shift_direction_at(900), Action::MoveLeft);
+        assert_eq!(shift_direction_at(1000), Action::MoveRight);
+        assert_eq!(shift_direction_at(1590), Action::MoveRight);
+        assert_eq!(shift_direction_at(2000), Action::MoveLeft);
+    }
+
+    #[test]
+    fn test_shift_to_wall() {
+        let build_game_state = || {
+            GameStateBuilder::new(10, 20)
+                .active_block(Block::new(BlockType::T), Vec2 { x: 4, y: 5 })
+                .build()
+        };
+
+        let mut shifted_game_state = build_game_state();
+        Action::ShiftLeftToWall.apply(&mut shifted_game_state);
+
+        let mut moved_game_state = build_game_state();
+        for _ in 0..10 {
+            Action::MoveLeft.apply(&mut moved_game_state);
+        }
+
+        assert_eq!(
+            render_board_text(&shifted_game_state),
+            render_board_text(&moved_game_state)
+        );
+        assert_ne!(
+            render_board_text(&shifted_game_state),
+            render_board_text(&build_game_state())
+        );
+    }
+
+    #[test]
+    fn test_config_parse() {
+        let config = Config::parse(
+            "[keys]\n\
+             hard_drop = \"up\"\n\
+             rotate_right = \"x\"\n\
+             [handling]\n\
+             das = 100 # milliseconds\n\
+             arr = 0\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.key_bindings.keys(Control::HardDrop), &[Key::Up]);
+        assert_eq!(config.handling.das, std::time::Duration::from_millis(100));
+        assert_eq!(config.handling.arr, std::time::Duration::from_millis(0));
+        assert_eq!(
+            config.handling.key_repeat_delay,
+            Handling::default().key_repeat_delay
+        );
+
+        // written configs read back the same
+        assert_eq!(Config::parse(config.to_config_string()), Ok(config));
+
+        assert!(Config::parse("[handling]\ndas = fast").is_err());
+        assert!(Config::parse("[handling]\ngravity = 1").is_err());
+        assert!(Config::parse("[handling]\nkey_release_time = 0").is_err());
+    }
+
+    #[test]
+    fn test_frame_pacer() {
+        use std::time::{Duration, Instant};
+
+        let mut frame_pacer = FramePacer::new(60);
+        assert_eq!(frame_pacer.frame_time(), Duration::from_micros(16_666));
+
+        let start = Instant::now();
+        assert_eq!(
+            frame_pacer.time_until_next_frame(start),
+            Duration::from_millis(0)
+        );
+        frame_pacer.start_frame(start);
+        assert_eq!(
+            frame_pacer.time_until_next_frame(start + Duration::from_millis(10)),
+            Duration::from_micros(6_666)
+        );
+
+        // frames are scheduled from when they were due so a late frame doesn't push back the rest
+        frame_pacer.start_frame(start + Duration::from_millis(20));
+        assert_eq!(
+            frame_pacer.time_until_next_frame(start + Duration::from_millis(20)),
+            Duration::from_micros(2 * 16_666 - 20_000)
+        );
+
+        // but falling far behind starts the schedule over instead of rushing to catch up
+        let much_later = start + Duration::from_secs(1);
+        frame_pacer.start_frame(much_later);
+        assert_eq!(
+            frame_pacer.time_until_next_frame(much_later),
+            frame_pacer.frame_time()
+        );
+    }
+
+    #[test]
+    fn test_fixed_timestep() {
+        use std::time::Duration;
+
+        let mut timestep = FixedTimestep::new(Duration::from_millis(10));
+        assert_eq!(timestep.advance(Duration::from_millis(4)), 0);
+        assert_eq!(timestep.advance(Duration::from_millis(7)), 1);
+        assert_eq!(timestep.advance(Duration::from_millis(29)), 3);
+        assert_eq!(timestep.advance(Duration::from_millis(9)), 0);
+        assert_eq!(timestep.advance(Duration::from_millis(1)), 1);
+    }
+
+    #[test]
+    fn test_frame_stats() {
+        use std::time::{Duration, Instant};
+
+        let mut frame_stats = FrameStats::new();
+        assert_eq!(frame_stats.fps(), 0);
+        assert_eq!(frame_stats.average_work_time(), Duration::from_millis(0));
+
+        let start = Instant::now();
+        for frame in 0..150 {
+            let work_time = Duration::from_millis(if frame % 2 == 0 { 1 } else { 3 });
+            frame_stats.record_frame(start + Duration::from_millis(frame * 10), work_time);
+        }
+
+        // only the last second counts
+        assert_eq!(frame_stats.fps(), 100);
+        assert_eq!(frame_stats.average_work_time(), Duration::from_millis(2));
+    }
+
+    #[test]
+    fn test_display_parse() {
+        let display = Display::parse("[display]\nframe_rate = 144\nshow_fps = true").unwrap();
+        assert_eq!(
+            display,
+            Display {
+                frame_rate: 144,
+                show_fps: true,
+                next_queue_len: GameStateBuilder::DEFAULT_NEXT_QUEUE_LEN,
+            }
+        );
+
+        let display = Display::parse("[display]\nnext_queue_len = 3").unwrap();
+        assert_eq!(display.next_queue_len, 3);
+        assert_eq!(Display::parse(display.to_config_string()), Ok(display));
+
+        assert!(Display::parse("[display]\nframe_rate = 0").is_err());
+        assert!(Display::parse("[display]\nshow_fps = yes").is_err());
+        assert!(Display::parse("[display]\nnext_queue_len = 2").is_err());
+        assert!(Display::parse("[display]\nnext_queue_len = 7").is_err());
+    }
+
+    #[test]
+    fn test_rules_parse() {
+        assert_eq!(Rules::parse(""), Ok(Rules::default()));
+        assert_eq!(Rules::default().rotation_system, RotationSystemType::Srs);
+
+        for rotation_system in RotationSystemType::ALL {
+            let rules = Rules { rotation_system };
+            assert_eq!(Rules::parse(rules.to_config_string()), Ok(rules));
+        }
+
+        let config = Config::parse("[rules]\nrotation_system = \"classic\"").unwrap();
+        assert_eq!(config.rules.rotation_system, RotationSystemType::Classic);
+
+        assert!(Rules::parse("[rules]\nrotation_system = \"nes\"").is_err());
+        assert!(Rules::parse("[rules]\nrotation_system = ars").is_err());
+        assert!(Rules::parse("[rules]\ngravity = 20").is_err());
+    }
+
+    // Compares everything drawn to a snapshot of the screen, ignoring the blank rows at the bottom
+    fn assert_screen(renderer: &GridRenderer, expected: &[&str]) {
+        let screen = renderer.to_text();
+        assert_eq!(screen.trim_end_matches('\n'), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_grid_renderer() {
+        let mut renderer = GridRenderer::new(6, 3);
+        assert_eq!(renderer.size(), (3, 6));
+
+        renderer.draw_frame(&Rect {
+            left: 0,
+            top: 0,
+            width: 4,
+            height: 3,
+        });
+        renderer.draw_text(2, 1, "hello", Style::PLAIN.blink());
+        // drawing off the screen is ignored
+        renderer.draw_char(-1, 0, 'x', Style::PLAIN);
+        renderer.draw_char(0, 3, 'x', Style::PLAIN);
+        assert_eq!(renderer.clipped_chars(), 3);
+        assert_screen(&renderer, &["+--+", "| hell", "+--+"]);
+        assert_eq!(renderer.cell(2, 1), Some(('h', Style::PLAIN.blink())));
+        assert_eq!(renderer.cell(6, 1), None);
+
+        renderer.clear();
+        assert_screen(&renderer, &[]);
+    }
+
+    #[test]
+    fn test_render_line_clear() {
+        let mut board = vec![vec![false; 10]; 20];
+        for row in &mut board[18..] {
+            row[..8].fill(true);
+        }
+
+        let mut game_state = GameStateBuilder::from_board(&board)
+            .active_block(Block::new(BlockType::O), Vec2 { x: 7, y: 0 })
+            .spawn_delay(SpawnDelay::standard())
+            .build();
+        game_state.hard_drop();
+
+        let mut renderer = GridRenderer::new(80, 24);
+        let layout = GameLayout::new(24, 80, game_state.next_queue_len());
+        let board_rect = &layout.board_rect;
+        let bottom_row = board_rect.top + 19;
+        let render = |renderer: &mut GridRenderer, game_state: &GameState| {
+            renderer.clear();
+            render_game(renderer, &layout, game_state, 1, None, false);
+        };
+
+        // the cleared rows flash
+        render(&mut renderer, &game_state);
+        for col in 0..10 {
+            let (sprite, style) = renderer.cell(board_rect.left + col, bottom_row).unwrap();
+            assert_ne!(sprite, ' ');
+            assert!(style.blink);
+        }
+
+        // then dissolve from the middle outwards
+        game_state.update(std::time::Duration::from_millis(150));
+        render(&mut renderer, &game_state);
+        let row: String = (0..10)
+            .map(|col| renderer.cell(board_rect.left + col, bottom_row).unwrap().0)
+            .collect();
+        assert_eq!(row, "OOO    OXX");
+
+        // and are gone once the line clear delay is over
+        game_state.update(std::time::Duration::from_millis(150));
+        render(&mut renderer, &game_state);
+        assert_eq!(
+            renderer.cell(board_rect.left, bottom_row),
+            Some((' ', Style::PLAIN))
+        );
+    }
+
+    #[test]
+    fn test_render_title_menu() {
+        let mut renderer = GridRenderer::new(80, 24);
+        render_title_menu(&mut renderer, &["Start Game", "Leaderboard", "Quit"], 1);
+
+        #[rustfmt::skip]
+        assert_screen(&renderer, &[
+            "",
+            "",
+            "",
+            "",
+            r#"               _____________"#,
+            r#"              /\____________\ ___  _____  ___  .   .   ___   _____"#,
+            r#"              \/___/\   \___/ \___    \   \ _)  \   \  \ ___    \"#,
+            r#"                   \ \   \     \___    \   \  \  \___\   ___\    \"#,
+            r#"                    \ \   \"#,
+            r#"                     \ \___\"#,
+            r#"                      \/___/"#,
+            "",
+            "",
+            r#"                                    Start Game"#,
+            r#"                                  > Leaderboard"#,
+            r#"                                    Quit"#,
+        ]);
+
+        // every line of the title card takes the next menu color
+        assert_eq!(
+            renderer.cell(15, 5),
+            Some(('\\', Style::color(Color::Menu(1))))
+        );
+    }
+
+    #[test]
+    fn test_render_game() {
+        let mut game_state = GameStateBuilder::standard(GameMode::Marathon, 1).build();
+        game_state.update(std::time::Duration::from_secs(1));
+        game_state.hard_drop();
+        game_state.update(std::time::Duration::from_secs(1));
+        game_state.hold();
+        game_state.update(std::time::Duration::from_secs(1));
+
+        let mut renderer = GridRenderer::new(80, 24);
+        let layout = GameLayout::new(24, 80, game_state.next_queue_len());
+        render_game(
+            &mut renderer,
+            &layout,
+            &game_state,
+            1,
+            Some("SINGLE"),
+            false,
+        );
+
+        #[rustfmt::skip]
+        assert_screen(&renderer, &[
+            "",
+            r#"                           +----------+ +---------+"#,
+            r#"                           |          | | TETRUST |"#,
+            r#"                           |          | +---------+"#,
+            r#"                    Hold   |          |  Next"#,
+            r#"                   +----+  |          | +----+"#,
+            r#"                   | XX |  |   OOOO   | |  \ |"#,
+            r#"                   | XX |  |   ----   | |\\\ |"#,
+            r#"                   +----+  |   ----   | |    |"#,
+            r#"                           |   ----   | | >> |"#,
+            r#"        +---------------+  |   ----   | |>>  |"#,
+            r#"        | Level: 00001  |  |   ----   | |    |"#,
+            r#"        | Score: 00034  |  |   ----   | |<<  |"#,
+            r#"        | Combo: 00000  |  |   ----   | | << |"#,
+            r#"        |               |  |   ----   | |    |"#,
+            r#"        |               |  |   ----   | |/   |"#,
+            r#"        +---------------+  |   ----   | |/// |"#,
+            r#"                           |   ----   | |    |"#,
+            r#"             SINGLE        |   ----   | | >> |"#,
+            r#"                           |   ----   | |>>  |"#,
+            r#"                           |   -+--   | +----+"#,
+            r#"                           |   +++-   |"#,
+            r#"                           +----------+ Seed: 1"#,
+        ]);
+
+        // the held piece is dimmed until it can be swapped back in
+        assert_eq!(
+            renderer.cell(21, 7),
+            Some(('X', Style::color(Color::Block(BlockType::O)).dim()))
+        );
+        assert_eq!(renderer.cell(13, 18), Some(('S', Style::PLAIN.blink())));
+
+        // pausing covers the middle of the board
+        render_game(&mut renderer, &layout, &game_state, 1, None, true);
+        let screen = renderer.to_text();
+        assert!(screen.lines().nth(12).unwrap().contains("|   PAUSE  |"));
+        assert!(!screen.contains("SINGLE"));
+    }
+
+    #[test]
+    fn test_render_leaderboard() {
+        let mut leaderboard = Leaderboard::new();
+        leaderboard.add_entry(
+            LeaderboardEntry::new("abc", 900).with_seed(7),
+            LeaderboardOrder::HighestFirst,
+        );
+        leaderboard.add_entry(
+            LeaderboardEntry::new("xyz", 500).with_seed(8),
+            LeaderboardOrder::HighestFirst,
+        );
+
+        // the entry being typed in pushes the entries below it down
+        let mut renderer = GridRenderer::new(80, 24);
+        let pending_entry = PendingEntry {
+            place: 1,
+            initials: "d__",
+            score: 700,
+            seed: 9,
+        };
+        render_leaderboard(
+            &mut renderer,
+            GameMode::Marathon,
+            &leaderboard,
+            Some(&pending_entry),
+        );
+
+        #[rustfmt::skip]
+        assert_screen(&renderer, &[
+            r#"             +----------------------------------------------------+"#,
+            r#"             |                Marathon Leaderboard                |"#,
+            r#"             |                                                    |"#,
+            r#"             |  #01    abc    00900    seed                    7  |"#,
+            r#"             |                                                    |"#,
+            r#"             |  #02    d__    00700    seed                    9  |"#,
+            r#"             |                                                    |"#,
+            r#"             |  #03    xyz    00500    seed                    8  |"#,
+            r#"             |                                                    |"#,
+            r#"             |  #04    ---    00000    seed                  ---  |"#,
+            r#"             |                                                    |"#,
+            r#"             |  #05    ---    00000    seed                  ---  |"#,
+            r#"             |                                                    |"#,
+            r#"             |  #06    ---    00000    seed                  ---  |"#,
+            r#"             |                                                    |"#,
+            r#"             |  #07    ---    00000    seed                  ---  |"#,
+            r#"             |                                                    |"#,
+            r#"             |  #08    ---    00000    seed                  ---  |"#,
+            r#"             |                                                    |"#,
+            r#"             |  #09    ---    00000    seed                  ---  |"#,
+            r#"             |                                                    |"#,
+            r#"             |  #10    ---    00000    seed                  ---  |"#,
+            r#"             +----------------------------------------------------+"#,
+        ]);
+        assert_eq!(renderer.cell(16, 5), Some(('#', Style::PLAIN.blink())));
+
+        render_leaderboard(&mut renderer, GameMode::sprint(), &leaderboard, None);
+        let screen = renderer.to_text();
+        assert!(screen.contains("Sprint Leaderboard"));
+        assert!(screen.contains("#02    xyz    00:00.500    seed                    8"));
+    }
+
+    #[test]
+    fn test_render_smallest_screen() {
+        let mut renderer = GridRenderer::new(MIN_SCREEN_WIDTH, MIN_SCREEN_HEIGHT);
+
+        // every screen fits, even with its longest text showing
+        let mut game_state = GameStateBuilder::standard(GameMode::sprint(), u64::MAX).build();
+        game_state.update(std::time::Duration::from_secs(1));
+        game_state.hold();
+        let layout = GameLayout::new(
+            MIN_SCREEN_HEIGHT,
+            MIN_SCREEN_WIDTH,
+            game_state.next_queue_len(),
+        );
+        render_game(
+            &mut renderer,
+            &layout,
+            &game_state,
+            u64::MAX,
+            Some("T-SPIN MINI DOUBLE"),
+            true,
+        );
+        render_replay_status(&mut renderer, &layout, "End of replay");
+        assert!(renderer.to_text().contains("End of replay"));
+        assert_eq!(renderer.clipped_chars(), 0);
+
+        // so does the longest next queue, which is packed tighter to fit beside the board
+        for next_queue_len in [3, 6] {
+            let game_state = GameStateBuilder::standard(GameMode::sprint(), u64::MAX)
+                .next_queue_len(next_queue_len)
+                .build();
+            let layout = GameLayout::new(MIN_SCREEN_HEIGHT, MIN_SCREEN_WIDTH, next_queue_len);
+            assert!(layout.preview_frame_rect.bottom() < layout.board_frame_rect.bottom());
+            render_game(&mut renderer, &layout, &game_state, u64::MAX, None, false);
+            assert_eq!(renderer.clipped_chars(), 0);
+        }
+
+        render_title_menu(
+            &mut renderer,
+            &[
+                "Start Game",
+                "Leaderboard",
+                "Watch Replay",
+                "Settings",
+                "Quit",
+            ],
+            0,
+        );
+
+        let mut leaderboard = Leaderboard::new();
+        for _ in 0..Leaderboard::max_entries() {
+            leaderboard.add_entry(
+                LeaderboardEntry::new("abc", 5999999).with_seed(u64::MAX),
+                LeaderboardOrder::LowestFirst,
+            );
+        }
+        let pending_entry = PendingEntry {
+            place: 0,
+            initials: "___",
+            score: 5999999,
+            seed: u64::MAX,
+        };
+        render_leaderboard(
+            &mut renderer,
+            GameMode::sprint(),
+            &leaderboard,
+            Some(&pending_entry),
+        );
+
+        render_settings(
+            &mut renderer,
+            &KeyBindings::default(),
+            Control::ALL.len() + 1,
+            "'a' is bound to both move_left and reset_speed",
+        );
+        assert_eq!(renderer.clipped_chars(), 0);
+        // the list scrolls down to the cursor
+        assert!(renderer.to_text().contains("> Back"));
+
+        // anything smaller asks for a bigger screen instead
+        for (width, height) in [
+            (MIN_SCREEN_WIDTH - 1, MIN_SCREEN_HEIGHT),
+            (MIN_SCREEN_WIDTH, MIN_SCREEN_HEIGHT - 1),
+        ]
+        .iter()
+        {
+            let mut renderer = GridRenderer::new(*width, *height);
+            let layout = GameLayout::new(*height, *width, game_state.next_queue_len());
+            render_game(&mut renderer, &layout, &game_state, 1, None, false);
+            let screen = renderer.to_text();
+            assert!(screen.contains("Please enlarge the terminal"));
+            assert!(!screen.contains(TITLE));
+
+            render_leaderboard(&mut renderer, GameMode::Marathon, &leaderboard, None);
+            assert!(renderer.to_text().contains("Please enlarge the terminal"));
+        }
+    }
+
+    #[test]
+    fn test_leaderboard() {
+        let mut leaderboard = Leaderboard::new();
+
+        struct TestData {
+            score: usize,
+            expected_pos: usize,
+        }
+
+        let test_values = [
+            TestData {
+                score: 700,
+                expected_pos: 0,
+            },
+            TestData {
+                score: 800,
+                expected_pos: 0,
+            },
+            TestData {
+                score: 750,
+                expected_pos: 1,
+            },
+            TestData {
+                score: 500,
+                expected_pos: 3,
+            },
+            TestData {
+                score: 200,
+                expected_pos: 4,
+            },
+            TestData {
+                score: 250,
+                expected_pos: 4,
+            },
+            TestData {
+                score: 300,
+                expected_pos: 4,
+            },
+            TestData {
+                score: 775,
+                expected_pos: 1,
+            },
+            TestData {
+                score: 100,
+                expected_pos: 8,
+            },
+            TestData {
+                score: 50,
+                expected_pos: 9,
+            },
+            TestData {
+                score: 200,
+                expected_pos: 8,
+            },
+        ];
+
+        fn name_from_index(i: usize) -> String {
+            format!("n{:02}", i)
+        }
+
+        let final_score_indices: [usize; Leaderboard::max_entries()] =
+            [1, 7, 2, 0, 3, 6, 5, 4, 10, 8];
         let expected_entries: Vec<LeaderboardEntry> = final_score_indices
             .iter()
             .map(|final_score_index| LeaderboardEntry {
                 name: name_from_index(*final_score_index),
                 score: test_values[*final_score_index].score,
+                seed: 0,
             })
             .collect();
         let expected_final_leaderboard = Leaderboard::from_raw(expected_entries);
 
         for (i, test) in test_values.iter().enumerate() {
-            let place = leaderboard.get_place_on_leaderboard(test.score);
+            let place =
+                leaderboard.get_place_on_leaderboard(test.score, LeaderboardOrder::HighestFirst);
             assert_eq!(place, Some(test.expected_pos));
 
             let name = name_from_index(i);
-            leaderboard.add_score(name, test.score);
+            leaderboard.add_score(name, test.score, LeaderboardOrder::HighestFirst);
         }
 
         // Verify that there are 10 entries on the leaderboard
@@ -663,6 +3613,27 @@ mod tests {
         assert_eq!(leaderboard, expected_final_leaderboard);
     }
 
+    #[test]
+    fn test_leaderboard_lowest_first() {
+        // timed modes rank the fastest times first
+        let mut leaderboard = Leaderboard::new();
+        for (name, time) in [("n00", 90_000), ("n01", 60_000), ("n02", 75_000)].iter() {
+            leaderboard.add_score(name, *time, LeaderboardOrder::LowestFirst);
+        }
+
+        assert_eq!(
+            leaderboard.get_place_on_leaderboard(70_000, LeaderboardOrder::LowestFirst),
+            Some(1)
+        );
+
+        let expected_final_leaderboard = Leaderboard::from_raw(vec![
+            LeaderboardEntry::new("n01", 60_000),
+            LeaderboardEntry::new("n02", 75_000),
+            LeaderboardEntry::new("n00", 90_000),
+        ]);
+        assert_eq!(leaderboard, expected_final_leaderboard);
+    }
+
     #[test]
     #[should_panic]
     fn test_leaderboard_add_fails() {
@@ -671,12 +3642,14 @@ mod tests {
         let mut leaderboard = Leaderboard::new();
 
         for _ in 0..Leaderboard::max_entries() {
-            assert!(leaderboard.get_place_on_leaderboard(TEST_SCORE).is_some());
-            leaderboard.add_score("test", TEST_SCORE);
+            assert!(leaderboard
+                .get_place_on_leaderboard(TEST_SCORE, LeaderboardOrder::HighestFirst)
+                .is_some());
+            leaderboard.add_score("test", TEST_SCORE, LeaderboardOrder::HighestFirst);
         }
 
         assert!(leaderboard
-            .get_place_on_leaderboard(TEST_SCORE - 1)
+            .get_place_on_leaderboard(TEST_SCORE - 1, LeaderboardOrder::HighestFirst)
             .is_none());
     }
 
@@ -689,11 +3662,15 @@ mod tests {
     #[test]
     fn test_new_leaderboard_from_file() {
         let mut dummy_leaderboard = Leaderboard::new();
-        dummy_leaderboard.add_score("ld5", 700);
-        dummy_leaderboard.add_score("ld3", 900);
-        dummy_leaderboard.add_score("ld4", 800);
-        dummy_leaderboard.add_score("ld1", 1000);
-        dummy_leaderboard.add_score("ld2", 1000);
+        dummy_leaderboard.add_score("ld5", 700, LeaderboardOrder::HighestFirst);
+        dummy_leaderboard.add_score("ld3", 900, LeaderboardOrder::HighestFirst);
+        dummy_leaderboard.add_score("ld4", 800, LeaderboardOrder::HighestFirst);
+        dummy_leaderboard.add_score("ld1", 1000, LeaderboardOrder::HighestFirst);
+        dummy_leaderboard.add_score("ld2", 1000, LeaderboardOrder::HighestFirst);
+        dummy_leaderboard.add_entry(
+            LeaderboardEntry::new("ld6", 600).with_seed(6),
+            LeaderboardOrder::HighestFirst,
+        );
 
         let dummy_file_name = "data/test_leaderboard";
         let _dummy_file = SelfDestructingFile::new(dummy_file_name, &dummy_leaderboard.serialize());
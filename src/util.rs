@@ -1,9 +1,11 @@
+/// A position or offset on the board. `x` grows to the right and `y` grows downwards.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vec2 {
     pub x: i32,
     pub y: i32,
 }
 
+/// A rectangle of cells with an inclusive right and bottom edge
 pub struct Rect {
     pub left: i32,
     pub top: i32,